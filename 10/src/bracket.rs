@@ -1,69 +1,264 @@
+use std::fs;
+
+/// A kind of bracket, which refers to one of the pairs in a [`BracketTable`].
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq, Hash)]
+pub struct Bracket(usize);
+
+impl Bracket {
+    /// Position of this bracket's pair in the table.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Either half of a bracket pair.
 #[derive(Debug)]
+#[derive(Clone, Copy)]
 #[derive(PartialEq)]
-pub enum Bracket {
-    Round,
-    Square,
-    Curly,
-    Angle,
+pub enum Token {
+    Open(Bracket),
+    Close(Bracket),
 }
 
-impl Bracket {
-    pub fn from_opening_char(c: char) -> Option<Self> {
-        match c {
-            '(' => Some(Self::Round),
-            '[' => Some(Self::Square),
-            '{' => Some(Self::Curly),
-            '<' => Some(Self::Angle),
-            _ => None,
+/// The text that opens and closes a kind of bracket, plus its scores.
+#[derive(Debug, Clone)]
+pub struct BracketPair {
+    pub open: String,
+    pub close: String,
+    /// Score for finding this as the wrong closing bracket.
+    pub score_if_invalid: u64,
+    /// Score for this needing to be closed to complete a line.
+    pub score_if_unmatched: u64,
+}
+
+impl BracketPair {
+    pub fn new(open: &str, close: &str, score_if_invalid: u64, score_if_unmatched: u64) -> Self {
+        Self {
+            open: open.to_string(),
+            close: close.to_string(),
+            score_if_invalid,
+            score_if_unmatched,
         }
     }
+}
 
-    pub fn from_closing_char(c: char) -> Option<Self> {
-        match c {
-            ')' => Some(Self::Round),
-            ']' => Some(Self::Square),
-            '}' => Some(Self::Curly),
-            '>' => Some(Self::Angle),
-            _ => None,
-        }
+/// The set of brackets that the checker knows about.
+///
+/// Brackets don't have to be single characters. Ones made of letters, like `begin` and `end`, only count as brackets
+/// when they're whole words.
+#[derive(Debug, Clone)]
+pub struct BracketTable {
+    pairs: Vec<BracketPair>,
+    /// Every opening and closing string, longest first, so that longer brackets win over any that are a prefix of them.
+    tokens: Vec<(String, Token)>,
+}
+
+impl Default for BracketTable {
+    /// The brackets and arbitrary scores provided by the problem.
+    fn default() -> Self {
+        Self::new(vec![
+            BracketPair::new("(", ")", 3, 1),
+            BracketPair::new("[", "]", 57, 2),
+            BracketPair::new("{", "}", 1197, 3),
+            BracketPair::new("<", ">", 25137, 4),
+        ])
+        .unwrap()
     }
+}
 
-    #[allow(dead_code)]
-    pub fn opening_char(&self) -> char {
-        match self {
-            Self::Round => '(',
-            Self::Square => '[',
-            Self::Curly => '{',
-            Self::Angle => '<',
-        }
+impl BracketTable {
+    /// The puzzle's brackets without `<>`, which are usually comparisons rather than brackets in source code.
+    pub fn structural() -> Self {
+        let mut pairs = Self::default().pairs;
+        pairs.retain(|p| p.open != "<");
+        Self::new(pairs).unwrap()
     }
 
-    #[allow(dead_code)]
-    pub fn closing_char(&self) -> char {
-        match self {
-            Self::Round => ')',
-            Self::Square => ']',
-            Self::Curly => '}',
-            Self::Angle => '>',
+    pub fn new(pairs: Vec<BracketPair>) -> Result<Self, String> {
+        let mut tokens: Vec<(String, Token)> = Vec::new();
+
+        for (i, pair) in pairs.iter().enumerate() {
+            let b = Bracket(i);
+            for (s, token) in [(&pair.open, Token::Open(b)), (&pair.close, Token::Close(b))] {
+                if s.is_empty() {
+                    return Err(format!("Bracket pair {} has an empty bracket", i + 1));
+                }
+                if tokens.iter().any(|(t, _)| t == s) {
+                    return Err(format!("Bracket `{}` is defined more than once", s));
+                }
+                tokens.push((s.clone(), token));
+            }
         }
+
+        tokens.sort_by_key(|(s, _)| std::cmp::Reverse(s.len()));
+
+        Ok(Self { pairs, tokens })
     }
 
-    /// Arbitrary score provided by the problem.
-    pub fn score_if_invalid(&self) -> i32 {
-        match self {
-            Self::Round => 3,
-            Self::Square => 57,
-            Self::Curly => 1197,
-            Self::Angle => 25137,
+    /// Parses a config file, where each line defines one pair of brackets like this:
+    /// ```text
+    /// # opening closing score-if-invalid score-if-unmatched
+    /// ( ) 3 1
+    /// begin end 10 5
+    /// ```
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn from_config(config: &str) -> Result<Self, String> {
+        let mut pairs = Vec::new();
+
+        for (i, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [open, close, invalid, unmatched] = parts[..] else {
+                return Err(format!("Line {}: expected 4 fields, found {}", i + 1, parts.len()));
+            };
+            let parse_score = |s: &str| {
+                s.parse::<u64>()
+                    .map_err(|e| format!("Line {}: invalid score `{}`: {}", i + 1, s, e))
+            };
+
+            pairs.push(BracketPair::new(open, close, parse_score(invalid)?, parse_score(unmatched)?));
         }
+
+        Self::new(pairs)
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let config = fs::read_to_string(filename).map_err(|e| format!("Can't read {}: {}", filename, e))?;
+        Self::from_config(&config)
+    }
+
+    /// Length in bytes of the longest opening or closing bracket.
+    pub fn max_token_len(&self) -> usize {
+        self.tokens.first().map_or(0, |(s, _)| s.len())
+    }
+
+    pub fn pair(&self, b: Bracket) -> &BracketPair {
+        &self.pairs[b.0]
+    }
+
+    pub fn pairs(&self) -> &[BracketPair] {
+        &self.pairs
     }
 
-    pub fn score_if_unmatched(&self) -> i32 {
-        match self {
-            Self::Round => 1,
-            Self::Square => 2,
-            Self::Curly => 3,
-            Self::Angle => 4,
+    pub fn brackets(&self) -> impl Iterator<Item = Bracket> {
+        (0..self.pairs.len()).map(Bracket)
+    }
+
+    /// Finds the bracket that opens with a particular string.
+    pub fn by_opening(&self, open: &str) -> Option<Bracket> {
+        self.pairs.iter().position(|p| p.open == open).map(Bracket)
+    }
+
+    pub fn opening(&self, b: Bracket) -> &str {
+        &self.pair(b).open
+    }
+
+    pub fn closing(&self, b: Bracket) -> &str {
+        &self.pair(b).close
+    }
+
+    pub fn score_if_invalid(&self, b: Bracket) -> u64 {
+        self.pair(b).score_if_invalid
+    }
+
+    pub fn score_if_unmatched(&self, b: Bracket) -> u64 {
+        self.pair(b).score_if_unmatched
+    }
+
+    /// Checks if there's a bracket at the start of `rest`, returning it and its length in bytes.
+    ///
+    /// `before` is the character just before `rest`, which is needed to know whether a word-like bracket is the start
+    /// of a word.
+    pub fn match_token(&self, before: Option<char>, rest: &str) -> Option<(Token, usize)> {
+        self.tokens
+            .iter()
+            .find(|(s, _)| {
+                if !rest.starts_with(s.as_str()) {
+                    return false;
+                }
+                if !is_word(s) {
+                    return true;
+                }
+                let after = rest[s.len()..].chars().next();
+                !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+            })
+            .map(|(s, token)| (*token, s.len()))
+    }
+
+    /// Finds all the brackets in a line, along with the column each one starts at (counting characters from 0).
+    pub fn tokens<'a>(&'a self, line: &'a str) -> impl Iterator<Item = (usize, Token)> + 'a {
+        self.spans(line).map(|s| (s.column, s.token))
+    }
+
+    /// Finds all the brackets in a line, along with where they are.
+    pub fn spans<'a>(&'a self, line: &'a str) -> impl Iterator<Item = Span> + 'a {
+        let mut pos = 0;
+        let mut column = 0;
+        let mut before = None;
+
+        std::iter::from_fn(move || {
+            while pos < line.len() {
+                let rest = &line[pos..];
+                let (found, len) = match self.match_token(before, rest) {
+                    Some((token, len)) => (Some(Span { start: pos, end: pos + len, column, token }), len),
+                    None => (None, rest.chars().next().unwrap().len_utf8()),
+                };
+
+                let skipped = &rest[..len];
+                before = skipped.chars().next_back();
+                column += skipped.chars().count();
+                pos += len;
+
+                if found.is_some() {
+                    return found;
+                }
+            }
+            None
+        })
+    }
+
+    /// The text for a token.
+    pub fn text(&self, token: Token) -> &str {
+        match token {
+            Token::Open(b) => self.opening(b),
+            Token::Close(b) => self.closing(b),
         }
     }
-}
\ No newline at end of file
+
+    /// Whether a token is a keyword, like `begin`, that needs to be a whole word.
+    pub fn is_word(&self, token: Token) -> bool {
+        is_word(self.text(token))
+    }
+
+    /// Every possible token, opening then closing for each bracket.
+    pub fn all_tokens(&self) -> impl Iterator<Item = Token> {
+        self.brackets().flat_map(|b| [Token::Open(b), Token::Close(b)])
+    }
+}
+
+/// Where a bracket was found in a line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    /// Byte offset of the start of the bracket.
+    pub start: usize,
+    /// Byte offset just after the end of the bracket.
+    pub end: usize,
+    /// Column the bracket starts at, counting characters from 0.
+    pub column: usize,
+    pub token: Token,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether a bracket is a keyword, like `begin`, that needs to be a whole word.
+fn is_word(s: &str) -> bool {
+    s.chars().any(is_word_char)
+}
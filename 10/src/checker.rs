@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+use crate::bracket::{Bracket, BracketTable, Token};
+use crate::diagnostic::Diagnostic;
+
+/// What's wrong with a line, if anything.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineStatus {
+    /// All the brackets match up.
+    Valid,
+    /// There's a closing bracket that doesn't match. Nothing after it gets checked.
+    Corrupted(Diagnostic),
+    /// Some brackets are never closed. These are in the order they were opened, so the last one needs closing first.
+    Incomplete(Vec<Bracket>),
+}
+
+/// The state of checking a single line, which can be fed to it a piece at a time.
+struct LineChecker {
+    line: usize,
+    column: usize,
+    /// The character before the next one to be checked, to tell whether a word-like bracket is a whole word.
+    before: Option<char>,
+    stack: Vec<Bracket>,
+    error: Option<Diagnostic>,
+    /// Whether anything has been seen since the start of the line, to match what `str::lines` counts as a line.
+    started: bool,
+}
+
+impl LineChecker {
+    fn new() -> Self {
+        Self {
+            line: 1,
+            column: 0,
+            before: None,
+            stack: Vec::new(),
+            error: None,
+            started: false,
+        }
+    }
+
+    /// Checks as much of `text` as it can, adding the status of any lines it finishes to `finished`.
+    ///
+    /// Unless `is_last` is set, it stops a little before the end of the text, because a bracket might be cut in half,
+    /// or a word-like bracket might turn out to be part of a longer word. Returns the number of bytes it got through.
+    fn process(
+        &mut self,
+        table: &BracketTable,
+        text: &str,
+        is_last: bool,
+        finished: &mut VecDeque<(usize, LineStatus)>,
+    ) -> usize {
+        // Enough for the longest bracket, plus the character after it.
+        let lookahead = if is_last { 0 } else { table.max_token_len() + 4 };
+        let mut pos = 0;
+
+        while pos < text.len() {
+            let rest = &text[pos..];
+
+            if rest.starts_with('\n') {
+                finished.push_back(self.end_line());
+                pos += 1;
+                continue;
+            }
+
+            if self.error.is_some() {
+                // Nothing else on a corrupted line matters, so skip straight to the end of it.
+                match rest.find('\n') {
+                    Some(i) => pos += i,
+                    None => return text.len(),
+                }
+                continue;
+            }
+
+            if rest.len() < lookahead && !rest.contains('\n') {
+                return pos;
+            }
+
+            self.started = true;
+            let len = match table.match_token(self.before, rest) {
+                Some((token, len)) => {
+                    self.push(token);
+                    len
+                }
+                None => rest.chars().next().unwrap().len_utf8(),
+            };
+
+            let skipped = &rest[..len];
+            self.before = skipped.chars().next_back();
+            self.column += skipped.chars().count();
+            pos += len;
+        }
+
+        pos
+    }
+
+    /// Skips over some bytes that aren't valid UTF-8. They can't be brackets, so they just count as one character.
+    fn skip_invalid(&mut self) {
+        self.started = true;
+        self.before = Some(char::REPLACEMENT_CHARACTER);
+        self.column += 1;
+    }
+
+    fn push(&mut self, token: Token) {
+        match token {
+            Token::Open(b) => self.stack.push(b),
+            Token::Close(b) => {
+                let top = self.stack.pop();
+                if top != Some(b) {
+                    self.error = Some(Diagnostic {
+                        line: self.line,
+                        column: self.column + 1,
+                        expected: top,
+                        found: Some(b),
+                        unterminated: None,
+                    });
+                    self.stack.clear();
+                }
+            }
+        }
+    }
+
+    /// Finishes the current line, and gets ready for the next one.
+    fn end_line(&mut self) -> (usize, LineStatus) {
+        let status = match self.error.take() {
+            Some(error) => LineStatus::Corrupted(error),
+            None if self.stack.is_empty() => LineStatus::Valid,
+            None => LineStatus::Incomplete(std::mem::take(&mut self.stack)),
+        };
+        let result = (self.line, status);
+
+        self.line += 1;
+        self.column = 0;
+        self.before = None;
+        self.started = false;
+
+        result
+    }
+}
+
+/// Checks a single line in one pass.
+///
+/// `line_number` starts from 1, and is just used to fill in the diagnostic if the line is corrupted.
+pub fn check_line(table: &BracketTable, line_number: usize, line: &str) -> LineStatus {
+    let mut checker = LineChecker::new();
+    checker.line = line_number;
+
+    let mut finished = VecDeque::new();
+    checker.process(table, line, true, &mut finished);
+
+    finished.pop_front().map_or_else(|| checker.end_line().1, |(_, status)| status)
+}
+
+/// Checks each line of a reader, a chunk at a time, without ever holding more than a chunk of it in memory.
+///
+/// Yields the line number (starting from 1) and status of each line. The only thing that grows with the input is the
+/// stack of open brackets, which is as deep as the brackets on a single line are nested.
+pub struct StreamChecker<'a, R> {
+    table: &'a BracketTable,
+    reader: R,
+    checker: LineChecker,
+    /// Bytes that have been read but not checked yet, because they're too close to the end of the chunk.
+    carry: Vec<u8>,
+    finished: VecDeque<(usize, LineStatus)>,
+    done: bool,
+}
+
+impl<'a, R: BufRead> StreamChecker<'a, R> {
+    pub fn new(table: &'a BracketTable, reader: R) -> Self {
+        Self {
+            table,
+            reader,
+            checker: LineChecker::new(),
+            carry: Vec::new(),
+            finished: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Checks as much of the carried over bytes as possible.
+    fn process_carry(&mut self, is_last: bool) {
+        let mut start = 0;
+
+        while start < self.carry.len() {
+            let bytes = &self.carry[start..];
+            let (valid, invalid_len) = match std::str::from_utf8(bytes) {
+                Ok(text) => (text, None),
+                Err(e) => (std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(), e.error_len()),
+            };
+            let is_all_valid = valid.len() == bytes.len();
+
+            // A bracket can't carry on past an invalid byte, so there's no need to wait for more input in that case.
+            let ends_here = is_last || invalid_len.is_some();
+            let consumed = self.checker.process(self.table, valid, ends_here, &mut self.finished);
+            start += consumed;
+            if consumed < valid.len() {
+                // Needs more input before it can carry on.
+                break;
+            }
+
+            match invalid_len {
+                Some(len) => {
+                    self.checker.skip_invalid();
+                    start += len;
+                }
+                // The chunk ends part way through a character. That's only a problem if there's no more input.
+                None if !is_all_valid && is_last => {
+                    self.checker.skip_invalid();
+                    start = self.carry.len();
+                }
+                None => break,
+            }
+        }
+
+        self.carry.drain(..start);
+    }
+}
+
+impl<R: BufRead> Iterator for StreamChecker<'_, R> {
+    type Item = io::Result<(usize, LineStatus)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.finished.pop_front() {
+                return Some(Ok(result));
+            }
+            if self.done {
+                return None;
+            }
+
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            if chunk.is_empty() {
+                self.done = true;
+                self.process_carry(true);
+                if self.checker.started {
+                    let result = self.checker.end_line();
+                    self.finished.push_back(result);
+                }
+                continue;
+            }
+
+            let len = chunk.len();
+            self.carry.extend_from_slice(chunk);
+            self.reader.consume(len);
+            self.process_carry(false);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::bracket::{Bracket, BracketTable};
+use crate::lexer::Unterminated;
+
+/// A closing bracket that doesn't match the bracket it should be closing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Line number, starting from 1.
+    pub line: usize,
+    /// Column of the bad bracket, counting characters from 1.
+    pub column: usize,
+    /// The closing bracket that should have been here, or None if there weren't any brackets open.
+    pub expected: Option<Bracket>,
+    /// The closing bracket that was found, or None if the file ended with `expected` still open. In that case, the line
+    /// and column point at the opening bracket that was never closed.
+    pub found: Option<Bracket>,
+    /// A string or comment that the file ended in the middle of. The line and column point at where it was opened,
+    /// and the brackets are both None.
+    pub unterminated: Option<Unterminated>,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic compiler-style, with the source line and a caret pointing at the bad bracket.
+    ///
+    /// Looks something like this:
+    /// ```text
+    /// error: expected `]`, found `}`
+    ///  --> demo.txt:3:13
+    ///   |
+    /// 3 | {([(<{}[<>[]}>{[]{[(<()>
+    ///   |             ^ expected `]`
+    /// ```
+    pub fn render(&self, table: &BracketTable, filename: &str, source_line: &str) -> String {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        // Keep tabs as tabs so the caret lines up however wide the terminal draws them.
+        let padding: String = source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let underlined = match (self.found, self.expected, &self.unterminated) {
+            (Some(found), _, _) => table.closing(found),
+            (None, Some(expected), _) => table.opening(expected),
+            (None, None, Some(Unterminated::String(open) | Unterminated::Comment(open))) => open,
+            (None, None, None) => " ",
+        };
+        let carets = "^".repeat(underlined.chars().count());
+
+        let label = match (self.found, self.expected) {
+            (Some(_), Some(b)) => format!("expected `{}`", table.closing(b)),
+            (Some(_), None) => "nothing to close".to_string(),
+            (None, _) => "never closed".to_string(),
+        };
+
+        let mut result = String::new();
+        result.push_str(&format!("error: {}\n", self.message(table)));
+        result.push_str(&format!("{}--> {}:{}:{}\n", gutter, filename, self.line, self.column));
+        result.push_str(&format!("{} |\n", gutter));
+        result.push_str(&format!("{} | {}\n", line_number, source_line));
+        result.push_str(&format!("{} | {}{} {}\n", gutter, padding, carets, label));
+        result
+    }
+
+    pub fn message(&self, table: &BracketTable) -> String {
+        match &self.unterminated {
+            Some(Unterminated::String(open)) => return format!("unclosed string `{}`", open),
+            Some(Unterminated::Comment(open)) => return format!("unclosed comment `{}`", open),
+            None => {}
+        }
+
+        match (self.found, self.expected) {
+            (Some(found), Some(expected)) => format!(
+                "expected `{}`, found `{}`",
+                table.closing(expected),
+                table.closing(found)
+            ),
+            (Some(found), None) => format!("unexpected closing bracket `{}`", table.closing(found)),
+            (None, Some(expected)) => format!("unclosed `{}`", table.opening(expected)),
+            (None, None) => "unexpected end of file".to_string(),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::bracket::{BracketTable, Span};
+
+/// Something like a string literal, where any brackets inside don't count.
+#[derive(Debug, Clone)]
+pub struct StringRule {
+    pub open: String,
+    pub close: String,
+    /// Character that stops the next character from closing the string.
+    pub escape: Option<char>,
+}
+
+impl StringRule {
+    pub fn new(open: &str, close: &str, escape: Option<char>) -> Self {
+        Self {
+            open: open.to_string(),
+            close: close.to_string(),
+            escape,
+        }
+    }
+}
+
+/// A comment that can span multiple lines, like `/* ... */`.
+#[derive(Debug, Clone)]
+pub struct BlockComment {
+    pub open: String,
+    pub close: String,
+    /// Whether comments can be nested inside each other, like in Rust.
+    pub nested: bool,
+}
+
+impl BlockComment {
+    pub fn new(open: &str, close: &str, nested: bool) -> Self {
+        Self {
+            open: open.to_string(),
+            close: close.to_string(),
+            nested,
+        }
+    }
+}
+
+/// Rules for the parts of a file where brackets aren't structural.
+#[derive(Debug, Clone, Default)]
+pub struct LexerRules {
+    /// Checked in order, so longer openings like `r#"` need to come before `"`.
+    pub strings: Vec<StringRule>,
+    pub line_comments: Vec<String>,
+    pub block_comments: Vec<BlockComment>,
+    /// Whether to skip Rust-style character literals like `'('`. These can't just be a string rule, because a single
+    /// quote is also used for lifetimes like `'a`.
+    pub char_literals: bool,
+}
+
+impl LexerRules {
+    /// No strings or comments, so every bracket counts. This is what the puzzle uses.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn rust() -> Self {
+        Self {
+            strings: vec![
+                StringRule::new("r##\"", "\"##", None),
+                StringRule::new("r#\"", "\"#", None),
+                StringRule::new("r\"", "\"", None),
+                StringRule::new("\"", "\"", Some('\\')),
+            ],
+            line_comments: vec!["//".to_string()],
+            block_comments: vec![BlockComment::new("/*", "*/", true)],
+            char_literals: true,
+        }
+    }
+
+    pub fn c() -> Self {
+        Self {
+            strings: vec![
+                StringRule::new("\"", "\"", Some('\\')),
+                StringRule::new("'", "'", Some('\\')),
+            ],
+            line_comments: vec!["//".to_string()],
+            block_comments: vec![BlockComment::new("/*", "*/", false)],
+            char_literals: false,
+        }
+    }
+
+    pub fn json() -> Self {
+        Self {
+            strings: vec![StringRule::new("\"", "\"", Some('\\'))],
+            ..Self::default()
+        }
+    }
+
+    /// Gets the rules for a language by name.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::none()),
+            "rust" => Some(Self::rust()),
+            "c" => Some(Self::c()),
+            "json" => Some(Self::json()),
+            _ => None,
+        }
+    }
+}
+
+/// A string or block comment that was never closed, along with the text that opened it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unterminated {
+    String(String),
+    Comment(String),
+}
+
+/// What the lexer is in the middle of. Strings and comments also have the line and column they were opened at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    /// Index of the string rule.
+    String(usize, (usize, usize)),
+    /// Index of the block comment rule, and how many levels deep we are.
+    BlockComment(usize, usize, (usize, usize)),
+}
+
+/// Finds the structural brackets in a file, one line at a time.
+///
+/// Strings and block comments can carry on to the next line, so the same lexer needs to be used for every line of the
+/// file, in order.
+pub struct Lexer<'a> {
+    table: &'a BracketTable,
+    rules: &'a LexerRules,
+    state: State,
+    /// How many lines have been lexed so far.
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(table: &'a BracketTable, rules: &'a LexerRules) -> Self {
+        Self { table, rules, state: State::Code, line: 0 }
+    }
+
+    /// Whether the end of the file would leave a string or comment unfinished.
+    pub fn in_code(&self) -> bool {
+        self.state == State::Code
+    }
+
+    /// The string or comment we're in the middle of, with the line and column it was opened at, counting from 1.
+    pub fn unterminated(&self) -> Option<(Unterminated, usize, usize)> {
+        match self.state {
+            State::Code => None,
+            State::String(i, (line, column)) => Some((Unterminated::String(self.rules.strings[i].open.clone()), line, column)),
+            State::BlockComment(i, _, (line, column)) => {
+                Some((Unterminated::Comment(self.rules.block_comments[i].open.clone()), line, column))
+            }
+        }
+    }
+
+    /// Goes back to code, as if whatever we were in the middle of had ended, and carries on from line `line`, counting
+    /// from 1.
+    pub fn restart_at(&mut self, line: usize) {
+        self.state = State::Code;
+        self.line = line - 1;
+    }
+
+    /// Finds the brackets in the next line that aren't in a string or comment.
+    pub fn line_spans(&mut self, line: &str) -> Vec<Span> {
+        self.line += 1;
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        let mut column = 0;
+        let mut before = None;
+
+        while pos < line.len() {
+            let rest = &line[pos..];
+            let len = match self.state {
+                State::Code => {
+                    if self.rules.line_comments.iter().any(|c| rest.starts_with(c.as_str())) {
+                        // Nothing else on this line matters.
+                        break;
+                    }
+                    self.lex_code(rest, before, pos, column, &mut spans)
+                }
+                State::String(i, _) => {
+                    let rule = &self.rules.strings[i];
+                    let mut chars = rest.chars();
+                    let c = chars.next().unwrap();
+                    if Some(c) == rule.escape {
+                        // Skip the escape character and whatever it's escaping.
+                        c.len_utf8() + chars.next().map_or(0, |c| c.len_utf8())
+                    } else if rest.starts_with(rule.close.as_str()) {
+                        self.state = State::Code;
+                        rule.close.len()
+                    } else {
+                        c.len_utf8()
+                    }
+                }
+                State::BlockComment(i, depth, opened) => {
+                    let rule = &self.rules.block_comments[i];
+                    if rest.starts_with(rule.close.as_str()) {
+                        self.state = if depth > 1 { State::BlockComment(i, depth - 1, opened) } else { State::Code };
+                        rule.close.len()
+                    } else if rule.nested && rest.starts_with(rule.open.as_str()) {
+                        self.state = State::BlockComment(i, depth + 1, opened);
+                        rule.open.len()
+                    } else {
+                        rest.chars().next().unwrap().len_utf8()
+                    }
+                }
+            };
+
+            let skipped = &rest[..len];
+            before = skipped.chars().next_back();
+            column += skipped.chars().count();
+            pos += len;
+        }
+
+        spans
+    }
+
+    /// Handles the start of `rest` when it's not in a string or comment, returning how many bytes to move forward.
+    fn lex_code(&mut self, rest: &str, before: Option<char>, pos: usize, column: usize, spans: &mut Vec<Span>) -> usize {
+        if let Some(i) = self.rules.block_comments.iter().position(|c| rest.starts_with(c.open.as_str())) {
+            self.state = State::BlockComment(i, 1, (self.line, column + 1));
+            return self.rules.block_comments[i].open.len();
+        }
+
+        // Strings can start with letters, like `r"`, so make sure it's not just the end of a word.
+        let is_word_start = !before.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let string = self.rules.strings.iter().position(|s| {
+            rest.starts_with(s.open.as_str()) && (is_word_start || !s.open.starts_with(char::is_alphanumeric))
+        });
+        if let Some(i) = string {
+            self.state = State::String(i, (self.line, column + 1));
+            return self.rules.strings[i].open.len();
+        }
+
+        if self.rules.char_literals {
+            if let Some(len) = char_literal_len(rest) {
+                return len;
+            }
+        }
+
+        if let Some((token, len)) = self.table.match_token(before, rest) {
+            spans.push(Span { start: pos, end: pos + len, column, token });
+            return len;
+        }
+
+        rest.chars().next().unwrap().len_utf8()
+    }
+}
+
+/// Gets the length of a character literal like `'('` or `'\u{7b}'` at the start of `s`, if there is one.
+fn char_literal_len(s: &str) -> Option<usize> {
+    let rest = s.strip_prefix('\'')?;
+    let mut chars = rest.char_indices();
+    let (_, c) = chars.next()?;
+
+    if c == '\\' {
+        // Escapes are short, so don't go looking too far for the closing quote.
+        return chars
+            .skip(1)
+            .take(10)
+            .find(|&(_, c)| c == '\'')
+            .map(|(i, _)| i + 2);
+    }
+
+    match chars.next() {
+        Some((i, '\'')) => Some(i + 2),
+        // Probably a lifetime.
+        _ => None,
+    }
+}
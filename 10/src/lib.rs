@@ -1,84 +1,169 @@
-use std::fs;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
 
 mod bracket;
+mod checker;
+mod diagnostic;
+mod lexer;
+mod repair;
+mod tree;
 
-use bracket::Bracket;
+pub use bracket::{Bracket, BracketPair, BracketTable, Token};
+pub use checker::{check_line, LineStatus, StreamChecker};
+pub use diagnostic::Diagnostic;
+pub use lexer::{BlockComment, Lexer, LexerRules, StringRule, Unterminated};
+pub use repair::{completion, find_repair, Edit, Repair};
+pub use tree::{BracketNode, BracketTree, TreeStats};
 
-fn first_invalid_bracket(line: &str) -> Option<Bracket> {
-    let mut stack = Vec::new();
+/// Finds all the lines with mismatched brackets.
+pub fn find_errors(table: &BracketTable, contents: &str) -> Vec<Diagnostic> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| match check_line(table, i + 1, line) {
+            LineStatus::Corrupted(error) => Some(error),
+            _ => None,
+        })
+        .collect()
+}
 
-    for c in line.chars() {
-        if let Some(b) = Bracket::from_opening_char(c) {
-            stack.push(b);
-        }
-        else if let Some(b) = Bracket::from_closing_char(c) {
-            if let Some(top) = stack.pop() {
-                if top != b {
-                    return Some(b);
+/// Checks a whole source file, where brackets can be split across lines, skipping over strings and comments.
+///
+/// After a mismatched closing bracket, the checker tries to carry on as if the mistake wasn't there: if it closes a
+/// bracket further down the stack, the ones above it are dropped, otherwise it's ignored. Brackets that are still open
+/// at the end of the file are reported too.
+///
+/// If the file ends in the middle of a string or comment, that's reported, and then the rest of the file is checked
+/// again as if it had ended at the end of the line it started on, so the brackets after it still get looked at.
+pub fn find_source_errors(table: &BracketTable, rules: &LexerRules, contents: &str) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut lexer = Lexer::new(table, rules);
+    let mut errors = Vec::new();
+    // Each open bracket, along with where it was opened.
+    let mut stack: Vec<(Bracket, usize, usize)> = Vec::new();
+    // Where to go back to if the last string or comment to be opened is never closed: the line after the one it was
+    // opened on, with the brackets and errors from before then.
+    let mut restart = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let opened_before = lexer.unterminated();
+        for span in lexer.line_spans(lines[i]) {
+            match span.token {
+                Token::Open(b) => stack.push((b, i + 1, span.column + 1)),
+                Token::Close(b) => {
+                    let top = stack.last().map(|(top, _, _)| *top);
+                    if top == Some(b) {
+                        stack.pop();
+                        continue;
+                    }
+
+                    errors.push(Diagnostic {
+                        line: i + 1,
+                        column: span.column + 1,
+                        expected: top,
+                        found: Some(b),
+                        unterminated: None,
+                    });
+                    if let Some(depth) = stack.iter().rposition(|(open, _, _)| *open == b) {
+                        stack.truncate(depth);
+                    }
                 }
             }
-            else {
-                return Some(b);
-            }
+        }
+        if !lexer.in_code() && lexer.unterminated() != opened_before {
+            restart = Some((i + 1, stack.clone(), errors.len()));
+        }
+        i += 1;
+
+        if i == lines.len() && !lexer.in_code() {
+            let (unterminated, line, column) = lexer.unterminated().unwrap();
+            let (restart_line, restart_stack, num_errors) = restart.take().unwrap();
+            stack = restart_stack;
+            errors.truncate(num_errors);
+            errors.push(Diagnostic { line, column, expected: None, found: None, unterminated: Some(unterminated) });
+
+            i = restart_line;
+            lexer.restart_at(i + 1);
         }
     }
 
-    None
-}
+    errors.extend(stack.into_iter().map(|(b, line, column)| Diagnostic {
+        line,
+        column,
+        expected: Some(b),
+        found: None,
+        unterminated: None,
+    }));
 
-pub fn solve_pt1(filename: &str) -> i32 {
-    // Read file into a string
-    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
+    errors
+}
 
-    contents
-        .lines()
-        .filter_map(first_invalid_bracket)
-        .map(|b| b.score_if_invalid())
-        .sum()
+/// The answers to both parts of the puzzle, which can be worked out together in a single pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scores {
+    /// Sum of the scores of the first invalid bracket on each corrupted line.
+    pub errors: u64,
+    /// The middle score of all the incomplete lines, where each line's score comes from the brackets needed to
+    /// complete it.
+    pub completion: u64,
 }
 
-/// Gets the brackets that don't have a closing bracket.
+/// Scores every line in a reader, without reading it all into memory first.
 ///
-/// If an invalid closing bracket is found, the line is invalid so we return None.
-fn get_unmatched_brackets(line: &str) -> Option<Vec<Bracket>> {
-    let mut stack = Vec::new();
+/// The only thing kept for every line is the completion score of the incomplete ones, which are needed to find the
+/// middle one at the end.
+pub fn score_stream(table: &BracketTable, reader: impl BufRead) -> io::Result<Scores> {
+    let mut errors = 0;
+    let mut completion_scores: Vec<u64> = Vec::new();
 
-    for c in line.chars() {
-        if let Some(b) = Bracket::from_opening_char(c) {
-            stack.push(b);
-        }
-        else if let Some(b) = Bracket::from_closing_char(c) {
-            if let Some(top) = stack.pop() {
-                if top != b {
-                    return None;
-                }
-            }
-            else {
-                return None;
-            }
+    for result in StreamChecker::new(table, reader) {
+        match result?.1 {
+            LineStatus::Valid => {}
+            LineStatus::Corrupted(error) => errors += error.found.map_or(0, |b| table.score_if_invalid(b)),
+            LineStatus::Incomplete(brackets) => completion_scores.push(
+                brackets.iter().rev().fold(0, |acc, &b| 5 * acc + table.score_if_unmatched(b)),
+            ),
         }
     }
 
-    Some(stack)
+    // Problem guarantees there will be an odd number of scores, but other inputs might not have any.
+    let middle = completion_scores.len() / 2;
+    let completion = if completion_scores.is_empty() {
+        0
+    } else {
+        *completion_scores.select_nth_unstable(middle).1
+    };
+
+    Ok(Scores { errors, completion })
 }
 
-pub fn solve_pt2(filename: &str) -> u64 {
-    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
+/// Scores a file using a particular set of brackets.
+pub fn score_file(table: &BracketTable, filename: &str) -> Scores {
+    let file = File::open(filename).expect("Something went wrong reading the file");
+    score_stream(table, BufReader::new(file)).expect("Something went wrong reading the file")
+}
 
-    let mut scores: Vec<u64> = contents
-        .lines()
-        .filter_map(get_unmatched_brackets)
-        .map(|brackets| brackets.iter().rev().fold(0, |acc, b| 5 * acc + b.score_if_unmatched() as u64))
-        .collect();
+pub fn score_errors(table: &BracketTable, contents: &str) -> u64 {
+    score_stream(table, contents.as_bytes()).unwrap().errors
+}
+
+pub fn score_completions(table: &BracketTable, contents: &str) -> u64 {
+    score_stream(table, contents.as_bytes()).unwrap().completion
+}
 
-    scores.sort();
-    // Problem guarantees there will be an odd number of scores.
-    scores[scores.len() / 2]
+pub fn solve_pt1(filename: &str) -> u64 {
+    score_file(&BracketTable::default(), filename).errors
+}
+
+pub fn solve_pt2(filename: &str) -> u64 {
+    score_file(&BracketTable::default(), filename).completion
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_pt1() {
@@ -89,4 +174,211 @@ mod tests {
     fn test_pt2() {
         assert_eq!(solve_pt2("demo.txt"), 288957);
     }
+
+    #[test]
+    fn test_diagnostics() {
+        let table = BracketTable::default();
+        let contents = fs::read_to_string("demo.txt").unwrap();
+        let errors = find_errors(&table, &contents);
+
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors[0], Diagnostic {
+            line: 3,
+            column: 13,
+            expected: table.by_opening("["),
+            found: table.by_opening("{"),
+            unterminated: None,
+        });
+
+        let rendered = errors[0].render(&table, "demo.txt", contents.lines().nth(2).unwrap());
+        assert_eq!(rendered, "\
+error: expected `]`, found `}`
+ --> demo.txt:3:13
+  |
+3 | {([(<{}[<>[]}>{[]{[(<()>
+  |             ^ expected `]`
+");
+    }
+
+    #[test]
+    fn test_diagnostic_nothing_open() {
+        let table = BracketTable::default();
+        let errors = find_errors(&table, "()\n())");
+        let round = table.by_opening("(").unwrap();
+        assert_eq!(errors, vec![Diagnostic { line: 2, column: 3, expected: None, found: Some(round), unterminated: None }]);
+    }
+
+    #[test]
+    fn test_completion() {
+        let table = BracketTable::default();
+        assert_eq!(completion(&table, "[({(<(())[]>[[{[]{<()<>>").as_deref(), Some("}}]])})]"));
+        assert_eq!(completion(&table, "[]").as_deref(), Some(""));
+        assert_eq!(completion(&table, "{([(<{}[<>[]}>{[]{[(<()>"), None);
+    }
+
+    #[test]
+    fn test_repair() {
+        let table = BracketTable::default();
+        let contents = fs::read_to_string("demo.txt").unwrap();
+
+        let repair = find_repair(&table, "{([(<{}[<>[]}>{[]{[(<()>").unwrap();
+        assert_eq!(repair.edit.describe(&table), "replace `}` with `]` at column 13");
+        assert_eq!(repair.line, "{([(<{}[<>[]]>{[]{[(<()>");
+
+        // An extra bracket gets deleted.
+        let repair = find_repair(&table, "(()))").unwrap();
+        assert_eq!(repair.edit.describe(&table), "delete `)` at column 5");
+
+        // Every corrupted line in the demo can be fixed with one change, and the others don't need fixing.
+        for line in contents.lines() {
+            let repair = find_repair(&table, line);
+            assert_eq!(repair.is_some(), completion(&table, line).is_none());
+        }
+    }
+
+    #[test]
+    fn test_source_rust() {
+        let table = BracketTable::structural();
+        let source = r#"
+fn main<'a>(x: &'a str) {
+    let s = "(not a bracket";
+    let c = '{';
+    let raw = r"[";
+    /* ( /* nested ] */ still a comment ) */
+    if x == ")" { // }
+        println!("{}", c);
+    }
+    foo(]);
+}
+"#;
+        let errors = find_source_errors(&table, &LexerRules::rust(), source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (10, 9));
+
+        // Without the lexer, loads of things look like errors.
+        assert!(find_source_errors(&table, &LexerRules::none(), source).len() > 1);
+
+        // A string that's never closed gets reported, and the brackets after it still get checked.
+        let source = "fn main() {\n    let s = \"oops;\n    foo(]);\n}\n";
+        let errors = find_source_errors(&table, &LexerRules::rust(), source);
+        let messages: Vec<(usize, usize, String)> = errors.iter().map(|e| (e.line, e.column, e.message(&table))).collect();
+        assert_eq!(
+            messages,
+            vec![
+                (2, 13, "unclosed string `\"`".to_string()),
+                (3, 9, "expected `)`, found `]`".to_string()),
+            ]
+        );
+        assert!(errors[0].render(&table, "main.rs", "    let s = \"oops;").ends_with("|             ^ never closed\n"));
+
+        // The same goes for comments, and the brackets before them are still open.
+        let source = "{ /* /* */\n(\n}";
+        let errors = find_source_errors(&table, &LexerRules::rust(), source);
+        let messages: Vec<(usize, usize, String)> = errors.iter().map(|e| (e.line, e.column, e.message(&table))).collect();
+        assert_eq!(
+            messages,
+            vec![
+                (1, 3, "unclosed comment `/*`".to_string()),
+                (3, 1, "expected `)`, found `}`".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_json_and_c() {
+        let table = BracketTable::structural();
+
+        let json = r#"{"a": ["}", "\"]"], "b": {}"#;
+        let errors = find_source_errors(&table, &LexerRules::json(), json);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message(&table), "unclosed `{`");
+        assert_eq!(errors[0].column, 1);
+
+        let c = "int f(char c) { /* } */ return c == '}' || c == \"(\"; }\n";
+        assert!(find_source_errors(&table, &LexerRules::c(), c).is_empty());
+    }
+
+    #[test]
+    fn test_stream_chunks() {
+        let table = BracketTable::from_config("« » 7 1\n( ) 3 1\nbegin end 100 2\n").unwrap();
+        let contents = "begin «(» beginning end\n\nbegin « end\r\nbegin «endless\n(\u{ff}\n«";
+        let expected: Vec<LineStatus> = contents.lines().enumerate().map(|(i, l)| check_line(&table, i + 1, l)).collect();
+
+        // Tiny chunks, so brackets and words get split between them.
+        for capacity in 1..8 {
+            let mut bytes = contents.as_bytes().to_vec();
+            // Some bytes that aren't valid UTF-8 shouldn't stop it.
+            let len = bytes.len();
+            bytes[len - 5] = 0xff;
+            let reader = BufReader::with_capacity(capacity, bytes.as_slice());
+            let statuses: Vec<LineStatus> = StreamChecker::new(&table, reader).map(|r| r.unwrap().1).collect();
+            assert_eq!(statuses, expected);
+        }
+
+        assert!(matches!(expected[2], LineStatus::Corrupted(Diagnostic { line: 3, column: 9, .. })));
+        assert_eq!(expected[1], LineStatus::Valid);
+    }
+
+    #[test]
+    fn test_tree() {
+        let table = BracketTable::default();
+
+        let tree = BracketTree::parse(&table, 1, "[<>({}){}[([])<>]]([").unwrap();
+        let stats = tree.stats(&table);
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(stats.counts, vec![3, 4, 2, 2]);
+        assert_eq!(stats.largest_subtree_size, 9);
+        assert_eq!(stats.largest_subtree_column, Some(1));
+
+        // The incomplete brackets at the end are still there, just not closed.
+        assert_eq!(tree.roots.len(), 2);
+        assert!(!tree.roots[1].closed);
+        assert_eq!(tree.roots[1].children[0].column, 20);
+
+        let error = BracketTree::parse(&table, 1, "(<)>").unwrap_err();
+        assert_eq!(error.column, 3);
+    }
+
+    #[test]
+    fn test_tree_export() {
+        let table = BracketTable::from_config("( ) 1 1\n\" \\ 1 1").unwrap();
+        let tree = BracketTree::parse(&table, 1, "(\"\\)(").unwrap();
+
+        assert_eq!(
+            tree.to_json(&table),
+            r#"{"roots":[{"open":"(","close":")","column":1,"closed":true,"children":[{"open":"\"","close":"\\","column":2,"closed":true,"children":[]}]},{"open":"(","close":")","column":5,"closed":false,"children":[]}]}"#
+        );
+
+        let dot = tree.to_dot(&table);
+        assert!(dot.contains(r#"n1 [label="\" \\\ncolumn 2"];"#));
+        assert!(dot.contains(r#"n2 [label="( \ncolumn 5", style=dashed];"#));
+        assert!(dot.contains("n0 -> n1;"));
+    }
+
+    #[test]
+    fn test_custom_brackets() {
+        let table = BracketTable::from_config("\
+# open close invalid unmatched
+« » 7 1
+begin end 100 2
+").unwrap();
+
+        // `endless` and `beginning` aren't brackets, because they aren't whole words.
+        let contents = "begin «» beginning end\nbegin « end\nbegin «endless\n";
+        let errors = find_errors(&table, contents);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert_eq!(errors[0].column, 9);
+        assert_eq!(score_errors(&table, contents), 100);
+
+        // Needs `» end` to complete it, so that's 1, then 5 * 1 + 2.
+        assert_eq!(score_completions(&table, contents), 7);
+    }
+
+    #[test]
+    fn test_bad_config() {
+        assert!(BracketTable::from_config("( ) 3").is_err());
+        assert!(BracketTable::from_config("( ) 3 x").is_err());
+        assert!(BracketTable::from_config("( ) 3 1\n[ ) 1 1").is_err());
+    }
 }
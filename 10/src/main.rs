@@ -1,17 +1,126 @@
 use advent2021_10::*;
-use std::env;
+use std::{env, fs, process};
+
+/// Checks the file like a linter, printing out every mismatched bracket.
+///
+/// Without any lexer rules, each line is checked separately like in the puzzle. With them, the file is checked as
+/// source code, so brackets can span lines.
+fn lint(table: &BracketTable, rules: Option<&LexerRules>, filename: &str) {
+    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let errors = match rules {
+        Some(rules) => find_source_errors(table, rules, &contents),
+        None => find_errors(table, &contents),
+    };
+    for error in &errors {
+        println!("{}", error.render(table, filename, lines[error.line - 1]));
+    }
+
+    if errors.is_empty() {
+        println!("{}: no errors", filename);
+    } else {
+        println!("{}: {} error(s)", filename, errors.len());
+        process::exit(1);
+    }
+}
+
+/// Writes out every line with its missing closing brackets added. Corrupted lines get repaired first if they can be,
+/// with the repair described on stderr so that stdout is just the fixed file.
+fn complete(table: &BracketTable, filename: &str) {
+    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = match find_repair(table, line) {
+            Some(repair) => {
+                eprintln!("{}:{}: {}", filename, i + 1, repair.edit.describe(table));
+                repair.line
+            }
+            None => line.to_string(),
+        };
+
+        match completion(table, &line) {
+            Some(closing) => println!("{}{}", line, closing),
+            None => {
+                eprintln!("{}:{}: can't be fixed by changing a single bracket", filename, i + 1);
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+/// Prints the nesting tree of every line that isn't corrupted, as dot or JSON lines.
+fn print_trees(table: &BracketTable, filename: &str, format: &str) {
+    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
+
+    for (i, line) in contents.lines().enumerate() {
+        match BracketTree::parse(table, i + 1, line) {
+            Ok(tree) if format == "dot" => print!("{}", tree.to_dot(table)),
+            Ok(tree) => println!("{}", tree.to_json(table)),
+            Err(error) => eprintln!("{}:{}: skipping, {}", filename, i + 1, error.message(table)),
+        }
+    }
+}
+
+fn usage(program: &str) -> ! {
+    panic!("Usage: {} [--lint [--lang <rust|c|json|none>] | --complete | --tree <dot|json>] [--brackets <config file>] <filename>", program);
+}
 
 fn main() {
     // Get the path to the file to read
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Usage: {} <filename>", args[0]);
+
+    let mut filename = None;
+    let mut lint_mode = false;
+    let mut complete_mode = false;
+    let mut tree_format = None;
+    let mut table = None;
+    let mut rules = None;
+
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--lint" => lint_mode = true,
+            "--complete" => complete_mode = true,
+            "--tree" => match rest.next().map(|s| s.as_str()) {
+                Some(format @ ("dot" | "json")) => tree_format = Some(format),
+                _ => usage(&args[0]),
+            },
+            "--brackets" => {
+                let config = rest.next().unwrap_or_else(|| usage(&args[0]));
+                table = Some(BracketTable::load(config).unwrap_or_else(|e| panic!("{}", e)));
+            }
+            "--lang" => {
+                let name = rest.next().unwrap_or_else(|| usage(&args[0]));
+                rules = Some(LexerRules::preset(name).unwrap_or_else(|| panic!("Unknown language {}", name)));
+            }
+            _ if filename.is_none() => filename = Some(arg),
+            _ => usage(&args[0]),
+        }
     }
-    let filename = &args[1];
+    let filename = filename.unwrap_or_else(|| usage(&args[0]));
 
-    let result = solve_pt1(filename);
-    println!("Part 1: {result}");
+    if lint_mode {
+        // `<` and `>` are usually operators in source code rather than brackets.
+        let table = match (table, &rules) {
+            (Some(table), _) => table,
+            (None, Some(_)) => BracketTable::structural(),
+            (None, None) => BracketTable::default(),
+        };
+        lint(&table, rules.as_ref(), filename);
+        return;
+    }
+    if complete_mode {
+        complete(&table.unwrap_or_default(), filename);
+        return;
+    }
+    if let Some(format) = tree_format {
+        print_trees(&table.unwrap_or_default(), filename, format);
+        return;
+    }
 
-    let result = solve_pt2(filename);
-    println!("Part 2: {result}");
+    // Both parts come out of the same pass over the file.
+    let scores = score_file(&table.unwrap_or_default(), filename);
+    println!("Part 1: {}", scores.errors);
+    println!("Part 2: {}", scores.completion);
 }
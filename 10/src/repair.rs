@@ -0,0 +1,150 @@
+use crate::bracket::{BracketTable, Span, Token};
+use crate::checker::{check_line, LineStatus};
+
+/// A change to a single bracket in a line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Replace { column: usize, old: Token, new: Token },
+    Insert { column: usize, token: Token },
+    Delete { column: usize, token: Token },
+}
+
+impl Edit {
+    /// Describes the edit, like "replace `}` with `]` at column 13". Columns count characters from 1.
+    pub fn describe(&self, table: &BracketTable) -> String {
+        match *self {
+            Edit::Replace { column, old, new } => {
+                format!("replace `{}` with `{}` at column {}", table.text(old), table.text(new), column)
+            }
+            Edit::Insert { column, token } => format!("insert `{}` at column {}", table.text(token), column),
+            Edit::Delete { column, token } => format!("delete `{}` at column {}", table.text(token), column),
+        }
+    }
+}
+
+/// A way to fix a corrupted line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    pub edit: Edit,
+    /// The line with the edit applied. It's no longer corrupted, but might still need completing.
+    pub line: String,
+}
+
+/// Gets the closing brackets that need to be added to the end of the line to complete it.
+///
+/// Returns None if the line is corrupted, because no amount of adding brackets on the end will fix it.
+pub fn completion(table: &BracketTable, line: &str) -> Option<String> {
+    let unmatched = match check_line(table, 1, line) {
+        LineStatus::Valid => Vec::new(),
+        LineStatus::Incomplete(unmatched) => unmatched,
+        LineStatus::Corrupted(_) => return None,
+    };
+
+    let mut result = String::new();
+    for &b in unmatched.iter().rev() {
+        let before = result.chars().next_back().or_else(|| line.chars().next_back());
+        push_token(table, &mut result, before, Token::Close(b));
+    }
+
+    Some(result)
+}
+
+/// Finds the smallest change to a corrupted line that stops it being corrupted: replacing, inserting or deleting a
+/// single bracket.
+///
+/// If there's more than one way to do it, we pick the one that leaves the fewest brackets unclosed, and then the one
+/// closest to where the error was found.
+///
+/// Returns None if the line isn't corrupted, or if it needs more than one change to fix it.
+pub fn find_repair(table: &BracketTable, line: &str) -> Option<Repair> {
+    let spans: Vec<Span> = table.spans(line).collect();
+    let error = check(spans.iter().map(|s| s.token)).err()?;
+
+    let mut best: Option<(usize, Repair)> = None;
+
+    // Changing anything after the first error won't help, so start at the error and work backwards.
+    for i in (0..=error).rev() {
+        let span = spans[i];
+        let column = span.column + 1;
+
+        let replacements = table
+            .all_tokens()
+            .filter(|&t| t != span.token)
+            .map(|new| Edit::Replace { column, old: span.token, new });
+        let deletion = Edit::Delete { column, token: span.token };
+        let insertions = table.all_tokens().map(|token| Edit::Insert { column, token });
+
+        for edit in replacements.chain([deletion]).chain(insertions) {
+            // The token that ends up at position i, and whether the existing token there is kept.
+            let (token, keep) = match edit {
+                Edit::Replace { new, .. } => (Some(new), false),
+                Edit::Delete { .. } => (None, false),
+                Edit::Insert { token, .. } => (Some(token), true),
+            };
+
+            let before = spans[..i].iter().map(|s| s.token);
+            let after = spans[i..].iter().skip(if keep { 0 } else { 1 }).map(|s| s.token);
+            let Ok(unclosed) = check(before.chain(token).chain(after)) else {
+                continue;
+            };
+            if best.as_ref().is_some_and(|(u, _)| *u <= unclosed) {
+                continue;
+            }
+
+            // Splicing text in can change what the neighbouring brackets look like, so check the final line too.
+            let end = if keep { span.start } else { span.end };
+            let repaired = splice(table, line, span.start, end, token);
+            if check(table.tokens(&repaired).map(|(_, t)| t)) == Ok(unclosed) {
+                best = Some((unclosed, Repair { edit, line: repaired }));
+            }
+        }
+    }
+
+    best.map(|(_, repair)| repair)
+}
+
+/// Checks a line's brackets, returning how many are left unclosed at the end, or the index of the first closing
+/// bracket that doesn't match.
+fn check(tokens: impl Iterator<Item = Token>) -> Result<usize, usize> {
+    let mut stack = Vec::new();
+
+    for (i, token) in tokens.enumerate() {
+        match token {
+            Token::Open(b) => stack.push(b),
+            Token::Close(b) => {
+                if stack.pop() != Some(b) {
+                    return Err(i);
+                }
+            }
+        }
+    }
+
+    Ok(stack.len())
+}
+
+/// Replaces the bytes from `start` to `end` with a token, or with nothing.
+fn splice(table: &BracketTable, line: &str, start: usize, end: usize, token: Option<Token>) -> String {
+    let mut result = line[..start].to_string();
+
+    if let Some(token) = token {
+        push_token(table, &mut result, line[..start].chars().next_back(), token);
+
+        // Keep word-like brackets separate from whatever's after them.
+        let after = line[end..].chars().next();
+        if table.is_word(token) && after.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            result.push(' ');
+        }
+    }
+
+    result.push_str(&line[end..]);
+    result
+}
+
+/// Adds a token to the end of a string, with a space before it if it's a word that would otherwise run into the
+/// previous one.
+fn push_token(table: &BracketTable, s: &mut String, before: Option<char>, token: Token) {
+    if table.is_word(token) && before.is_some_and(|c| !c.is_whitespace()) {
+        s.push(' ');
+    }
+    s.push_str(table.text(token));
+}
@@ -0,0 +1,200 @@
+use crate::bracket::{Bracket, BracketTable, Token};
+use crate::diagnostic::Diagnostic;
+
+/// A pair of brackets, and all the brackets nested directly inside it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BracketNode {
+    pub bracket: Bracket,
+    /// Column of the opening bracket, counting characters from 1.
+    pub column: usize,
+    /// False if the line ended before this bracket was closed.
+    pub closed: bool,
+    pub children: Vec<BracketNode>,
+}
+
+impl BracketNode {
+    /// Number of bracket pairs in this subtree, including this one.
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(|c| c.size()).sum::<usize>()
+    }
+}
+
+/// Some numbers about how the brackets in a line are nested.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    /// How deeply nested the deepest bracket is, where the outermost brackets are at depth 1.
+    pub max_depth: usize,
+    /// The number of each kind of bracket, indexed by the bracket's position in the table.
+    pub counts: Vec<usize>,
+    /// Size of the largest top level group of brackets, as a number of bracket pairs.
+    pub largest_subtree_size: usize,
+    /// Column where the largest top level group starts.
+    pub largest_subtree_column: Option<usize>,
+}
+
+/// The brackets in a line, arranged by how they're nested.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BracketTree {
+    /// The top level brackets, that aren't inside any others.
+    pub roots: Vec<BracketNode>,
+}
+
+impl BracketTree {
+    /// Builds the tree for a line that's valid or incomplete. Incomplete lines end up with some nodes that aren't closed.
+    ///
+    /// A corrupted line doesn't have a sensible tree, so we return the diagnostic instead. `line_number` is just used
+    /// to fill that in.
+    pub fn parse(table: &BracketTable, line_number: usize, line: &str) -> Result<Self, Diagnostic> {
+        // The brackets that are still open, each holding the children found so far.
+        let mut open: Vec<BracketNode> = Vec::new();
+        let mut roots = Vec::new();
+
+        for (column, token) in table.tokens(line) {
+            match token {
+                Token::Open(bracket) => open.push(BracketNode {
+                    bracket,
+                    column: column + 1,
+                    closed: false,
+                    children: Vec::new(),
+                }),
+                Token::Close(b) => {
+                    let mut node = match open.pop() {
+                        Some(node) if node.bracket == b => node,
+                        top => {
+                            return Err(Diagnostic {
+                                line: line_number,
+                                column: column + 1,
+                                expected: top.map(|n| n.bracket),
+                                found: Some(b),
+                                unterminated: None,
+                            })
+                        }
+                    };
+                    node.closed = true;
+                    match open.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => roots.push(node),
+                    }
+                }
+            }
+        }
+
+        // Anything left open gets attached to whatever it's inside, from the inside out.
+        while let Some(node) = open.pop() {
+            match open.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+
+        Ok(Self { roots })
+    }
+
+    /// Every node in the tree along with its depth, parents before their children.
+    pub fn nodes(&self) -> Vec<(&BracketNode, usize)> {
+        let mut result = Vec::new();
+        let mut to_visit: Vec<(&BracketNode, usize)> = self.roots.iter().rev().map(|n| (n, 1)).collect();
+
+        while let Some((node, depth)) = to_visit.pop() {
+            result.push((node, depth));
+            to_visit.extend(node.children.iter().rev().map(|c| (c, depth + 1)));
+        }
+
+        result
+    }
+
+    pub fn stats(&self, table: &BracketTable) -> TreeStats {
+        let nodes = self.nodes();
+
+        let mut counts = vec![0; table.pairs().len()];
+        for (node, _) in &nodes {
+            counts[node.bracket.index()] += 1;
+        }
+
+        // A subtree is always smaller than the one it's inside, so the largest is one of the top level ones.
+        let largest = self.roots.iter().map(|r| (r.size(), r.column)).max_by_key(|&(size, column)| {
+            // Prefer the first one if there's a tie.
+            (size, std::cmp::Reverse(column))
+        });
+
+        TreeStats {
+            max_depth: nodes.iter().map(|&(_, depth)| depth).max().unwrap_or(0),
+            counts,
+            largest_subtree_size: largest.map_or(0, |(size, _)| size),
+            largest_subtree_column: largest.map(|(_, column)| column),
+        }
+    }
+
+    /// Outputs the tree as a dot file. Brackets that were never closed are dashed.
+    pub fn to_dot(&self, table: &BracketTable) -> String {
+        let mut dot = String::new();
+
+        dot.push_str("digraph G {\n");
+        dot.push_str("  root [label=\"line\", shape=box];\n");
+
+        let mut next_id = 0;
+        let mut to_visit: Vec<(&BracketNode, String)> =
+            self.roots.iter().rev().map(|n| (n, "root".to_string())).collect();
+        while let Some((node, parent)) = to_visit.pop() {
+            let id = format!("n{}", next_id);
+            next_id += 1;
+
+            let open = escape(table.opening(node.bracket));
+            let close = if node.closed { escape(table.closing(node.bracket)) } else { String::new() };
+            let style = if node.closed { "" } else { ", style=dashed" };
+            dot.push_str(&format!(
+                "  {} [label=\"{} {}\\ncolumn {}\"{}];\n",
+                id, open, close, node.column, style
+            ));
+            dot.push_str(&format!("  {} -> {};\n", parent, id));
+
+            to_visit.extend(node.children.iter().rev().map(|c| (c, id.clone())));
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Outputs the tree as JSON, like `{"roots":[{"open":"(","close":")","column":1,"closed":true,"children":[]}]}`.
+    pub fn to_json(&self, table: &BracketTable) -> String {
+        let mut json = String::new();
+        json.push_str("{\"roots\":");
+        nodes_to_json(table, &self.roots, &mut json);
+        json.push('}');
+        json
+    }
+}
+
+fn nodes_to_json(table: &BracketTable, nodes: &[BracketNode], json: &mut String) {
+    json.push('[');
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&format!(
+            "{{\"open\":\"{}\",\"close\":\"{}\",\"column\":{},\"closed\":{},\"children\":",
+            escape(table.opening(node.bracket)),
+            escape(table.closing(node.bracket)),
+            node.column,
+            node.closed,
+        ));
+        nodes_to_json(table, &node.children, json);
+        json.push('}');
+    }
+    json.push(']');
+}
+
+/// Escapes a string so it can go inside quotes in JSON or dot.
+fn escape(s: &str) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
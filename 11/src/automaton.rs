@@ -0,0 +1,129 @@
+use crate::grid::Grid;
+use crate::topology::Topology;
+
+/// The rules for a cellular automaton.
+///
+/// Each step happens in three stages:
+/// 1. Every cell is updated at the same time, based on its own state and its neighbours' states from the last step.
+/// 2. Any cells that are ready to fire do so, which affects their neighbours, which might then fire too, and so on
+///    until things settle down.
+/// 3. Every cell gets a chance to tidy up, e.g. to clear a flag saying that it's fired.
+///
+/// Rules that don't cascade, like the Game of Life, can leave the stage 2 and 3 methods as they are.
+pub trait Rule {
+    type Cell: Clone;
+
+    /// Works out the new state of a cell at the start of a step.
+    fn update<'a>(&self, cell: &Self::Cell, neighbours: impl Iterator<Item = &'a Self::Cell>) -> Self::Cell
+    where
+        Self::Cell: 'a;
+
+    /// Whether a cell is ready to fire.
+    fn should_fire(&self, _cell: &Self::Cell) -> bool {
+        false
+    }
+
+    /// Fires a cell. If it's still ready to fire afterwards, it'll fire again.
+    fn fire(&self, _cell: &mut Self::Cell) {}
+
+    /// Updates a cell when one of its neighbours fires.
+    fn receive(&self, _cell: &mut Self::Cell) {}
+
+    /// Tidies up a cell at the end of a step.
+    fn finish(&self, _cell: &mut Self::Cell) {}
+}
+
+/// A cell firing during a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Firing {
+    pub x: usize,
+    pub y: usize,
+    /// How far along the cascade it was. Cells that were ready to fire at the start of the cascade are in wave 0, the
+    /// cells they set off are in wave 1, and so on.
+    pub wave: usize,
+}
+
+/// A grid of cells, plus the rules for how they change.
+#[derive(Debug, Clone)]
+pub struct Automaton<R: Rule> {
+    pub rule: R,
+    pub grid: Grid<R::Cell>,
+    pub topology: Topology,
+}
+
+impl<R: Rule> Automaton<R> {
+    /// Creates an automaton where each cell has 8 neighbours, and the edges don't wrap around.
+    pub fn new(rule: R, grid: Grid<R::Cell>) -> Self {
+        Self::with_topology(rule, grid, Topology::default())
+    }
+
+    pub fn with_topology(rule: R, grid: Grid<R::Cell>, topology: Topology) -> Self {
+        Self { rule, grid, topology }
+    }
+
+    /// Gets the positions of the cells next to a cell.
+    pub fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.topology.neighbours(self.grid.width, self.grid.height, x, y)
+    }
+
+    /// Runs a single step, returning how many times cells fired.
+    pub fn step(&mut self) -> u64 {
+        self.update_cells();
+        let num_fired = self.cascade(|_| {});
+        self.grid.cells_mut().iter_mut().for_each(|c| self.rule.finish(c));
+        num_fired
+    }
+
+    /// Runs a single step, returning every time a cell fired, in the order they happened.
+    pub fn step_recorded(&mut self) -> Vec<Firing> {
+        let mut firings = Vec::new();
+        self.update_cells();
+        self.cascade(|firing| firings.push(firing));
+        self.grid.cells_mut().iter_mut().for_each(|c| self.rule.finish(c));
+        firings
+    }
+
+    fn update_cells(&mut self) {
+        let next = Grid::from_fn(self.grid.width, self.grid.height, |x, y| {
+            let neighbours = self.neighbours(x, y).map(|(nx, ny)| self.grid.get(nx, ny));
+            self.rule.update(self.grid.get(x, y), neighbours)
+        });
+        self.grid = next;
+    }
+
+    fn cascade(&mut self, mut on_fire: impl FnMut(Firing)) -> u64 {
+        let mut num_fired = 0;
+        // Each cell is stored along with the wave it'll be in if it fires.
+        let mut to_fire: Vec<(usize, usize, usize)> = self
+            .grid
+            .positions()
+            .filter(|&(x, y)| self.rule.should_fire(self.grid.get(x, y)))
+            .map(|(x, y)| (x, y, 0))
+            .collect();
+
+        while let Some((x, y, wave)) = to_fire.pop() {
+            // Cells can be added to the list more than once, so check they still need to fire when they come off it.
+            if !self.rule.should_fire(self.grid.get(x, y)) {
+                continue;
+            }
+
+            self.rule.fire(self.grid.get_mut(x, y));
+            num_fired += 1;
+            on_fire(Firing { x, y, wave });
+            if self.rule.should_fire(self.grid.get(x, y)) {
+                to_fire.push((x, y, wave + 1));
+            }
+
+            let neighbours: Vec<(usize, usize)> = self.neighbours(x, y).collect();
+            for (nx, ny) in neighbours {
+                let neighbour = self.grid.get_mut(nx, ny);
+                self.rule.receive(neighbour);
+                if self.rule.should_fire(neighbour) {
+                    to_fire.push((nx, ny, wave + 1));
+                }
+            }
+        }
+
+        num_fired
+    }
+}
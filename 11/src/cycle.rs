@@ -0,0 +1,166 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::automaton::{Automaton, Rule};
+
+/// Where an automaton starts repeating itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// The number of steps before the first state that comes round again.
+    pub start: u64,
+    /// The number of steps it takes to come round again.
+    pub period: u64,
+}
+
+/// Everything that happens up to the end of the first time round the cycle. Since every step after that is a repeat
+/// of one of these, this is enough to say what happens on any step, however far ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleAnalysis {
+    pub cycle: Cycle,
+    /// How many cells fired on each step, starting from step 1, for `cycle.start + cycle.period` steps.
+    fired: Vec<u64>,
+}
+
+impl CycleAnalysis {
+    /// Makes an analysis from the cycle and how many cells fired on each step, starting from step 1. There has to be
+    /// at least one step in the cycle, and a count for every step up to the end of the first time round it.
+    pub fn new(cycle: Cycle, fired: Vec<u64>) -> Result<Self, String> {
+        if cycle.period == 0 {
+            return Err("A cycle has to be at least one step long".to_string());
+        }
+        let steps = cycle.start + cycle.period;
+        if (fired.len() as u64) < steps {
+            return Err(format!("Need how many fired on each of the first {} steps, but only got {}", steps, fired.len()));
+        }
+        Ok(Self { cycle, fired })
+    }
+
+    /// How many cells fired on each step, starting from step 1.
+    pub fn fired(&self) -> &[u64] {
+        &self.fired
+    }
+
+    /// Gets how many cells fire on a step, counting from 1. Nothing fires on step 0, since that's before the first
+    /// step.
+    pub fn fired_on(&self, step: u64) -> u64 {
+        let Cycle { start, period } = self.cycle;
+        if step == 0 {
+            return 0;
+        }
+        let step = if step <= start + period { step } else { start + 1 + (step - start - 1) % period };
+        self.fired[step as usize - 1]
+    }
+
+    /// Gets how many cells fire in total over the given number of steps.
+    pub fn total_fired(&self, steps: u64) -> u64 {
+        let Cycle { start, period } = self.cycle;
+        let sum = |steps: u64| self.fired[..steps as usize].iter().sum::<u64>();
+
+        if steps <= start + period {
+            return sum(steps);
+        }
+
+        let before = sum(start);
+        let per_cycle = sum(start + period) - before;
+        let (cycles, remainder) = ((steps - start) / period, (steps - start) % period);
+        before + cycles * per_cycle + (sum(start + remainder) - before)
+    }
+
+    /// Finds the first step where the number of cells that fire matches the condition, or None if it never does.
+    pub fn first_step(&self, condition: impl Fn(u64) -> bool) -> Option<u64> {
+        // Any step after these is just a repeat of one of them.
+        self.fired.iter().position(|&fired| condition(fired)).map(|i| i as u64 + 1)
+    }
+}
+
+/// An automaton along with a hash of its grid, so most states can be told apart without comparing every cell.
+#[derive(Clone)]
+struct State<R: Rule> {
+    automaton: Automaton<R>,
+    hash: u64,
+}
+
+impl<R: Rule + Clone> State<R>
+where
+    R::Cell: Hash + Eq,
+{
+    fn new(automaton: Automaton<R>) -> Self {
+        let hash = hash_grid(&automaton);
+        Self { automaton, hash }
+    }
+
+    fn step(&mut self) {
+        self.automaton.step();
+        self.hash = hash_grid(&self.automaton);
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.automaton.grid == other.automaton.grid
+    }
+}
+
+fn hash_grid<R: Rule>(automaton: &Automaton<R>) -> u64
+where
+    R::Cell: Hash,
+{
+    let mut hasher = DefaultHasher::new();
+    automaton.grid.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Finds where an automaton starts repeating itself, using Brent's algorithm.
+///
+/// This only ever keeps two states around, so it works for big grids with long cycles. A grid only has so many
+/// possible states, so there's always a cycle eventually, but it might take a very long time to find if the cells can
+/// hold lots of different values.
+pub fn find_cycle<R: Rule + Clone>(automaton: &Automaton<R>) -> Cycle
+where
+    R::Cell: Hash + Eq,
+{
+    let start = State::new(automaton.clone());
+
+    // Find the period, by moving the tortoise up to the hare at every power of two.
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = start.clone();
+    hare.step();
+    while !tortoise.same(&hare) {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare.step();
+        period += 1;
+    }
+
+    // Then find the start, by having the hare a whole period ahead of the tortoise, and moving them both until they
+    // meet.
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..period {
+        hare.step();
+    }
+    let mut cycle_start = 0;
+    while !tortoise.same(&hare) {
+        tortoise.step();
+        hare.step();
+        cycle_start += 1;
+    }
+
+    Cycle { start: cycle_start, period }
+}
+
+/// Finds the cycle, and records what happens up to the end of the first time round it.
+pub fn analyse_cycle<R: Rule + Clone>(automaton: &Automaton<R>) -> CycleAnalysis
+where
+    R::Cell: Hash + Eq,
+{
+    let cycle = find_cycle(automaton);
+
+    let mut automaton = automaton.clone();
+    let fired = (0..cycle.start + cycle.period).map(|_| automaton.step()).collect();
+
+    CycleAnalysis::new(cycle, fired).expect("Brent's algorithm always finds a cycle at least one step long")
+}
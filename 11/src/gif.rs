@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+/// Writes an animated GIF that loops forever.
+///
+/// Each frame is a list of indexes into the palette, row by row, and is shown for `delay` hundredths of a second. The
+/// palette can have up to 256 colours.
+pub fn encode_gif(width: u16, height: u16, palette: &[[u8; 3]], frames: &[Vec<u8>], delay: u16) -> Vec<u8> {
+    assert!(!palette.is_empty() && palette.len() <= 256, "A GIF palette needs between 1 and 256 colours");
+
+    // The colour table has to have a power of two entries, and at least 4.
+    let bits = (palette.len().next_power_of_two().trailing_zeros() as u8).max(2);
+
+    let mut gif = Vec::new();
+    gif.extend_from_slice(b"GIF89a");
+
+    // Logical screen descriptor, with a global colour table.
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    gif.push(0x80 | ((bits - 1) << 4) | (bits - 1));
+    gif.push(0);
+    gif.push(0);
+
+    for i in 0..1 << bits {
+        gif.extend_from_slice(&palette.get(i).copied().unwrap_or([0, 0, 0]));
+    }
+
+    // Netscape extension to make it loop.
+    gif.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    for frame in frames {
+        assert_eq!(frame.len(), width as usize * height as usize, "Frames need to fill the whole image");
+
+        // Graphic control extension, for the delay.
+        gif.extend_from_slice(b"\x21\xf9\x04\x00");
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(b"\x00\x00");
+
+        // Image descriptor, covering the whole image.
+        gif.push(0x2c);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.push(0);
+
+        gif.push(bits);
+        for block in lzw_compress(bits, frame).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0);
+    }
+
+    gif.push(0x3b);
+    gif
+}
+
+/// Writes codes of varying sizes, packing them into bytes starting from the lowest bit.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.len;
+        self.len += size;
+        while self.len >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Compresses a frame with the variable length LZW that GIFs use.
+fn lzw_compress(min_code_size: u8, pixels: &[u8]) -> Vec<u8> {
+    const MAX_CODE: u16 = 4096;
+
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut writer = BitWriter { bytes: Vec::new(), buffer: 0, len: 0 };
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size as u32 + 1;
+
+    writer.write(clear, code_size);
+
+    let Some((&first, rest)) = pixels.split_first() else {
+        writer.write(end, code_size);
+        return writer.finish();
+    };
+
+    let mut current = first as u16;
+    for &pixel in rest {
+        if let Some(&code) = codes.get(&(current, pixel)) {
+            current = code;
+            continue;
+        }
+
+        writer.write(current, code_size);
+        if next_code == MAX_CODE {
+            // The table's full, so start again.
+            writer.write(clear, code_size);
+            codes.clear();
+            next_code = end + 1;
+            code_size = min_code_size as u32 + 1;
+        } else {
+            if next_code >= 1 << code_size {
+                code_size += 1;
+            }
+            codes.insert((current, pixel), next_code);
+            next_code += 1;
+        }
+        current = pixel as u16;
+    }
+
+    writer.write(current, code_size);
+    writer.write(end, code_size);
+    writer.finish()
+}
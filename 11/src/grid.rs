@@ -0,0 +1,87 @@
+/// A rectangle of cells, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |r| r.len());
+        assert!(rows.iter().all(|r| r.len() == width), "All the rows need to be the same length");
+        Self {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        }
+    }
+
+    pub fn from_fn(width: usize, height: usize, f: impl Fn(usize, usize) -> T) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Self { width, height, cells }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> &T {
+        &self.cells[y * self.width + x]
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> &mut T {
+        &mut self.cells[y * self.width + x]
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn cells_mut(&mut self) -> &mut [T] {
+        &mut self.cells
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Makes a grid of the same size, with each cell converted by `f`.
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// The position of every cell, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+}
+
+impl Grid<u8> {
+    /// Parses a grid of single digits, one row per line.
+    pub fn from_digits(s: &str) -> Self {
+        Self::from_rows(
+            s.lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    line.chars()
+                        .filter_map(|c| c.to_digit(10).map(|x| x as u8))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::{thread, time::Duration};
+
+use crate::automaton::{Automaton, Firing, Rule};
+use crate::gif::encode_gif;
+use crate::grid::Grid;
+
+/// How many different colours are used for energy levels, and for the waves of a cascade.
+pub const SHADES: usize = 10;
+
+/// What happened during a single step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepRecord<C> {
+    /// Every cell that fired, in the order they fired.
+    pub firings: Vec<Firing>,
+    /// The grid at the end of the step.
+    pub grid: Grid<C>,
+}
+
+impl<C> StepRecord<C> {
+    /// How many waves the cascade took.
+    pub fn waves(&self) -> usize {
+        self.firings.iter().map(|f| f.wave + 1).max().unwrap_or(0)
+    }
+}
+
+/// How a cell looks in one frame of an animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pixel {
+    /// A cell that's not firing, with how full of energy it is, from 0 up to `SHADES - 1`.
+    Energy(usize),
+    /// A cell that's fired, with the wave of the cascade it fired in.
+    Fired(usize),
+}
+
+impl Pixel {
+    /// Energy levels go from dark to bright blue, and the waves of a cascade go from white to red.
+    pub fn colour(&self) -> [u8; 3] {
+        let (from, to, i) = match *self {
+            Pixel::Energy(shade) => ([10, 10, 40], [60, 140, 255], shade),
+            Pixel::Fired(wave) => ([255, 255, 255], [255, 60, 0], wave),
+        };
+        let t = i.min(SHADES - 1) as f64 / (SHADES - 1) as f64;
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        [mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2])]
+    }
+
+    fn palette_index(&self) -> u8 {
+        match *self {
+            Pixel::Energy(shade) => shade.min(SHADES - 1) as u8,
+            Pixel::Fired(wave) => (SHADES + wave.min(SHADES - 1)) as u8,
+        }
+    }
+}
+
+/// A single picture in an animation of the history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The step being shown, where 0 is before the first step.
+    pub step: usize,
+    /// The wave of the cascade being shown, or None for the end of the step.
+    pub wave: Option<usize>,
+    pub pixels: Grid<Pixel>,
+}
+
+/// Everything that happened over a number of steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History<C> {
+    /// The grid before the first step.
+    pub initial: Grid<C>,
+    pub steps: Vec<StepRecord<C>>,
+}
+
+impl<C: Clone> History<C> {
+    /// Runs an automaton for a number of steps, recording what happens.
+    pub fn record<R: Rule<Cell = C>>(automaton: &mut Automaton<R>, steps: usize) -> Self {
+        let initial = automaton.grid.clone();
+        let steps = (0..steps)
+            .map(|_| {
+                let firings = automaton.step_recorded();
+                StepRecord { firings, grid: automaton.grid.clone() }
+            })
+            .collect();
+
+        Self { initial, steps }
+    }
+
+    /// Turns the history into frames. Each step gets a frame for every wave of its cascade, building up the cells that
+    /// have fired so far on top of the energy levels from the start of the step, and then a frame for the end of the
+    /// step.
+    ///
+    /// `shade` says how full of energy a cell is, from 0 up to `SHADES - 1`.
+    pub fn frames(&self, shade: impl Fn(&C) -> usize) -> Vec<Frame> {
+        let mut frames = vec![Frame { step: 0, wave: None, pixels: self.initial.map(|c| Pixel::Energy(shade(c))) }];
+
+        for (i, record) in self.steps.iter().enumerate() {
+            let mut pixels = frames.last().unwrap().pixels.clone();
+            for wave in 0..record.waves() {
+                for firing in record.firings.iter().filter(|f| f.wave == wave) {
+                    *pixels.get_mut(firing.x, firing.y) = Pixel::Fired(wave);
+                }
+                frames.push(Frame { step: i + 1, wave: Some(wave), pixels: pixels.clone() });
+            }
+
+            frames.push(Frame { step: i + 1, wave: None, pixels: record.grid.map(|c| Pixel::Energy(shade(c))) });
+        }
+
+        frames
+    }
+}
+
+/// Draws a frame for the terminal, two characters per cell so they come out roughly square.
+pub fn render_ansi(frame: &Frame) -> String {
+    let mut buffer = String::new();
+
+    let _ = match frame.wave {
+        Some(wave) => writeln!(buffer, "Step {}, wave {}\x1b[K", frame.step, wave + 1),
+        None => writeln!(buffer, "Step {}\x1b[K", frame.step),
+    };
+
+    for row in frame.pixels.rows() {
+        for pixel in row {
+            let [r, g, b] = pixel.colour();
+            let _ = write!(buffer, "\x1b[48;2;{};{};{}m  ", r, g, b);
+        }
+        buffer.push_str("\x1b[0m\n");
+    }
+
+    buffer
+}
+
+/// Plays the frames in the terminal.
+pub fn replay(frames: &[Frame], fps: f64) {
+    let delay = if fps > 0.0 { Duration::from_secs_f64(1.0 / fps) } else { Duration::ZERO };
+
+    // Clear the screen once. Each frame after that just moves the cursor back to the top left.
+    print!("\x1b[2J");
+    for frame in frames {
+        let mut stdout = io::stdout().lock();
+        // Not much we can do if the terminal's gone away.
+        let _ = write!(stdout, "\x1b[H{}", render_ansi(frame));
+        let _ = stdout.flush();
+        drop(stdout);
+
+        thread::sleep(delay);
+    }
+}
+
+/// Encodes the frames as an animated GIF, with each cell drawn as a `scale` by `scale` square.
+pub fn frames_to_gif(frames: &[Frame], scale: usize, fps: f64) -> Vec<u8> {
+    let Some(first) = frames.first() else {
+        return encode_gif(1, 1, &[[0, 0, 0]], &[], 0);
+    };
+    let (width, height) = (first.pixels.width * scale, first.pixels.height * scale);
+
+    let palette: Vec<[u8; 3]> = (0..SHADES)
+        .map(Pixel::Energy)
+        .chain((0..SHADES).map(Pixel::Fired))
+        .map(|p| p.colour())
+        .collect();
+
+    let images: Vec<Vec<u8>> = frames
+        .iter()
+        .map(|frame| {
+            let pixels = &frame.pixels;
+            Grid::from_fn(width, height, |x, y| pixels.get(x / scale, y / scale).palette_index())
+                .cells()
+                .to_vec()
+        })
+        .collect();
+
+    // GIF delays are in hundredths of a second.
+    let delay = if fps > 0.0 { (100.0 / fps).round() as u16 } else { 0 };
+    encode_gif(width as u16, height as u16, &palette, &images, delay)
+}
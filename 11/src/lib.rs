@@ -1,126 +1,394 @@
+use std::fmt;
 use std::fs;
 
-const DELTAS: [(i32, i32); 8] = [
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, -1),
-    (1, 0),
-    (1, 1),
-];
+mod automaton;
+mod cycle;
+mod gif;
+mod grid;
+mod history;
+mod life;
+mod octopus;
+mod sandpile;
+mod topology;
 
-const FLASHED_MASK: u8 = 1 << 7;
+pub use automaton::{Automaton, Firing, Rule};
+pub use cycle::{analyse_cycle, find_cycle, Cycle, CycleAnalysis};
+pub use gif::encode_gif;
+pub use grid::Grid;
+pub use history::{frames_to_gif, render_ansi, replay, Frame, History, Pixel, StepRecord, SHADES};
+pub use life::Life;
+pub use octopus::{Octopus, OctopusCell};
+pub use sandpile::Sandpile;
+pub use topology::{Boundary, Neighbourhood, Topology};
 
-fn parse(filename: &str) -> Vec<Vec<u8>> {
+/// Reads the octopuses' energy levels from a file, with the given rules and topology.
+pub fn load_octopuses(filename: &str, rule: Octopus, topology: Topology) -> Automaton<Octopus> {
     let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
-    contents
-        .lines()
-        .filter(|line| !line.is_empty())
-        .map(|line| {
-            line.chars()
-                .filter_map(|c| c.to_digit(10).map(|x| x as u8))
-                .collect()
-        })
-        .collect()
+    let grid = Grid::from_digits(&contents).map(|&energy| OctopusCell::new(energy as u32));
+    Automaton::with_topology(rule, grid, topology)
 }
 
-fn increase_energy_levels(grid: &mut [Vec<u8>]) {
-    grid.iter_mut()
-        .for_each(|r| r.iter_mut().for_each(|x| *x += 1));
+fn parse(filename: &str) -> Automaton<Octopus> {
+    load_octopuses(filename, Octopus::default(), Topology::moore())
 }
 
-fn flash(grid: &mut [Vec<u8>]) -> u64 {
-    let mut num_flashed = 0;
-    let mut to_flash: Vec<(usize, usize)> = Vec::new();
-
-    to_flash.extend(grid.iter().enumerate().flat_map(|(y, r)| {
-        r.iter()
-            .enumerate()
-            .filter(|(_, &o)| o >= 10)
-            .map(move |(x, _)| (x, y))
-    }));
-
-    while let Some((x, y)) = to_flash.pop() {
-        // Check if already flashed.
-        if grid[y][x] & FLASHED_MASK > 0 {
-            continue;
+pub fn solve_pt1(filename: &str) -> u64 {
+    let mut octopuses = parse(filename);
+
+    (0..100).map(|_| octopuses.step()).sum()
+}
+
+/// When all the octopuses first flash at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronisation {
+    At(u64),
+    /// They've started going round in a cycle without ever all flashing together, so they never will.
+    Never,
+}
+
+impl fmt::Display for Synchronisation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Synchronisation::At(step) => write!(f, "{}", step),
+            Synchronisation::Never => write!(f, "never synchronises"),
         }
+    }
+}
 
-        // Flash!
-        num_flashed += 1;
+pub fn solve_pt2(filename: &str) -> Synchronisation {
+    let octopuses = parse(filename);
+    let size = octopuses.grid.len() as u64;
 
-        // Increase neighbours
-        for (dx, dy) in DELTAS.iter() {
-            let nx = x as i32 + dx;
-            let ny = y as i32 + dy;
+    match analyse_cycle(&octopuses).first_step(|fired| fired == size) {
+        Some(step) => Synchronisation::At(step),
+        None => Synchronisation::Never,
+    }
+}
 
-            if nx < 0 || ny < 0 || nx >= grid[0].len() as i32 || ny >= grid.len() as i32 {
-                continue;
-            }
+/// Runs the octopuses for a number of steps, and turns what happens into frames for an animation.
+pub fn flash_frames(filename: &str, steps: usize) -> Vec<Frame> {
+    let mut octopuses = parse(filename);
+    let rule = octopuses.rule;
+    History::record(&mut octopuses, steps).frames(|cell| rule.shade(cell, SHADES))
+}
 
-            let nx = nx as usize;
-            let ny = ny as usize;
+/// Works out how many flashes there'll be after a given number of steps, without having to simulate them all.
+pub fn predict_flashes(filename: &str, steps: u64) -> (Cycle, u64) {
+    let analysis = analyse_cycle(&parse(filename));
+    (analysis.cycle, analysis.total_fired(steps))
+}
 
-            grid[ny][nx] += 1;
-            if grid[ny][nx] >= 10 {
-                // Add to the to_flash list. The check for if it's already flashed will be done when it's popped from the list.
-                to_flash.push((nx, ny));
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pt1() {
+        assert_eq!(solve_pt1("demo.txt"), 1656);
+    }
+
+    #[test]
+    fn test_pt2() {
+        assert_eq!(solve_pt2("demo.txt"), Synchronisation::At(195));
+        assert_eq!(solve_pt2("never.txt"), Synchronisation::Never);
+    }
+
+    #[test]
+    fn test_cycle() {
+        // Once they've all flashed together, they keep doing so every 10 steps.
+        let analysis = analyse_cycle(&parse("demo.txt"));
+        assert_eq!(analysis.cycle, Cycle { start: 195, period: 10 });
+
+        let mut octopuses = parse("demo.txt");
+        let mut total = 0;
+        for step in 1..=400 {
+            total += octopuses.step();
+            assert_eq!(analysis.total_fired(step), total);
         }
+        assert_eq!(analysis.fired_on(0), 0);
+        assert_eq!(analysis.fired_on(195), 100);
+        assert_eq!(analysis.fired_on(1_000_000_000_005), 100);
+        assert_eq!(analysis.fired_on(1_000_000_000_007), analysis.fired_on(197));
+        assert!(CycleAnalysis::new(analysis.cycle, analysis.fired()[..204].to_vec()).is_err());
+        assert!(CycleAnalysis::new(Cycle { start: 0, period: 0 }, Vec::new()).is_err());
+
+        let before_sync = analysis.total_fired(195);
+        assert_eq!(predict_flashes("demo.txt", 1_000_000_000_000).1, before_sync + 100 * ((1_000_000_000_000 - 195) / 10));
 
-        // Mark as flashed
-        grid[y][x] |= 1 << 7;
+        // The 5 flashes and gives the 0 enough energy to flash 4 steps later, and then it's back to the start.
+        assert_eq!(find_cycle(&parse("never.txt")), Cycle { start: 0, period: 9 });
     }
 
-    num_flashed
-}
+    #[test]
+    fn test_topology_neighbours() {
+        let count = |topology: Topology, x, y| topology.neighbours(5, 4, x, y).count();
+
+        assert_eq!(count(Topology::moore(), 0, 0), 3);
+        assert_eq!(count(Topology::moore().wrapped(), 0, 0), 8);
+        assert_eq!(count(Topology::von_neumann(), 0, 0), 2);
+        assert_eq!(count(Topology::von_neumann(), 2, 2), 4);
+        assert_eq!(count(Topology::hex(), 2, 2), 6);
+        assert_eq!(count(Topology::custom(vec![(2, 0), (-2, 0)]), 1, 0), 1);
+
+        // Odd rows are shifted right, so the cells above are the same column and the one to the right.
+        let mut above: Vec<_> = Topology::hex().neighbours(5, 4, 2, 1).filter(|&(_, y)| y == 0).collect();
+        above.sort();
+        assert_eq!(above, vec![(2, 0), (3, 0)]);
+
+        let wrapped: Vec<_> = Topology::von_neumann().wrapped().neighbours(5, 4, 0, 3).collect();
+        assert_eq!(wrapped, vec![(4, 3), (1, 3), (0, 2), (0, 0)]);
+    }
+
+    #[test]
+    fn test_flash_topology() {
+        // A single octopus about to flash in the corner, and which others it gives energy to.
+        let energised = |topology, size| {
+            let grid = Grid::from_fn(size, size, |x, y| OctopusCell::new(if (x, y) == (0, 0) { 9 } else { 0 }));
+            let mut octopuses = Automaton::with_topology(Octopus::default(), grid, topology);
+            assert_eq!(octopuses.step(), 1);
+            octopuses.grid.positions().filter(|&(x, y)| octopuses.grid.get(x, y).energy == 2).collect::<Vec<_>>()
+        };
+
+        assert_eq!(energised(Topology::moore(), 5).len(), 3);
+        assert_eq!(energised(Topology::moore().wrapped(), 5).len(), 8);
+        assert_eq!(energised(Topology::von_neumann(), 5).len(), 2);
+
+        // Hex grids need an even height to wrap. Row 0 is even, so the cells above it are on the left, which wraps
+        // round to the bottom row, and the right hand edge.
+        let mut hex = energised(Topology::hex().wrapped(), 6);
+        hex.sort();
+        assert_eq!(hex, [(0, 1), (0, 5), (1, 0), (5, 0), (5, 1), (5, 5)]);
+    }
+
+    #[test]
+    fn test_low_threshold() {
+        fn energies(octopuses: &Automaton<Octopus>) -> Vec<u32> {
+            octopuses.grid.cells().iter().map(|c| c.energy).collect()
+        }
 
-fn reset_flashed(grid: &mut [Vec<u8>]) {
-    grid.iter_mut().for_each(|r| {
-        r.iter_mut().for_each(|x| {
-            if *x > 10 {
-                *x = 0
+        let row = Grid::from_rows(vec![vec![0, 1, 2]]).map(|&energy| OctopusCell::new(energy));
+        let mut octopuses = Automaton::new(Octopus::new(3, 0), row.clone());
+
+        // The right one flashes, which sets off the middle one.
+        assert_eq!(octopuses.step(), 2);
+        assert_eq!(energies(&octopuses), vec![2, 0, 0]);
+        assert_eq!(octopuses.step(), 1);
+        assert_eq!(energies(&octopuses), vec![0, 2, 1]);
+
+        let mut octopuses = Automaton::new(Octopus::new(3, 1), row);
+        assert_eq!(octopuses.step(), 2);
+        assert_eq!(energies(&octopuses), vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn test_high_threshold() {
+        let rule = Octopus::new(200, 0);
+
+        let mut octopuses = Automaton::new(rule, Grid::from_fn(1, 1, |_, _| OctopusCell::new(0)));
+        assert_eq!((1..).find(|_| octopuses.step() == 1), Some(200));
+
+        // Every octopus gets 8 lots of energy from its neighbours, which would overflow a u8 with a flag in the top bit.
+        let mut octopuses = Automaton::new(rule, Grid::from_fn(3, 3, |_, _| OctopusCell::new(199)));
+        assert_eq!(octopuses.step(), 9);
+        assert!(octopuses.grid.cells().iter().all(|&c| c == OctopusCell::new(0)));
+
+        let grid = Grid::from_fn(3, 3, |x, _| OctopusCell::new(if x == 0 { 199 } else { 120 }));
+        let mut octopuses = Automaton::new(rule, grid);
+        assert_eq!(octopuses.step(), 3);
+        assert_eq!(octopuses.grid.get(1, 1).energy, 124);
+        assert_eq!(octopuses.grid.get(2, 1).energy, 121);
+    }
+
+    #[test]
+    fn test_history() {
+        let row = Grid::from_rows(vec![vec![0, 1, 2]]).map(|&energy| OctopusCell::new(energy));
+        let mut octopuses = Automaton::new(Octopus::new(3, 0), row);
+        let history = History::record(&mut octopuses, 2);
+
+        assert_eq!(
+            history.steps[0].firings,
+            vec![Firing { x: 2, y: 0, wave: 0 }, Firing { x: 1, y: 0, wave: 1 }]
+        );
+        assert_eq!(history.steps[1].firings, vec![Firing { x: 0, y: 0, wave: 0 }]);
+
+        // The start, two waves and the end of the first step, then one wave and the end of the second.
+        let frames = history.frames(|cell| cell.energy as usize);
+        assert_eq!(frames.len(), 6);
+        assert_eq!(frames[2].wave, Some(1));
+        assert_eq!(frames[2].pixels.cells(), &[Pixel::Energy(0), Pixel::Fired(1), Pixel::Fired(0)]);
+        assert_eq!(frames[3].pixels.cells(), &[Pixel::Energy(2), Pixel::Energy(0), Pixel::Energy(0)]);
+
+        let gif = frames_to_gif(&frames, 4, 10.0);
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        // Each frame should come back out as the palette index of each pixel.
+        let index = |pixel: &Pixel| match *pixel {
+            Pixel::Energy(shade) => shade as u8,
+            Pixel::Fired(wave) => (SHADES + wave) as u8,
+        };
+        let expected: Vec<Vec<u8>> = frames.iter().map(|frame| frame.pixels.cells().iter().map(index).collect()).collect();
+        assert_eq!(decode_gif(&frames_to_gif(&frames, 1, 10.0)), expected);
+    }
+
+    /// Reads the frames back out of a GIF, as palette indexes. This only handles what `encode_gif` writes.
+    fn decode_gif(gif: &[u8]) -> Vec<Vec<u8>> {
+        let bits = (gif[10] & 7) + 1;
+        let mut i = 13 + 3 * (1 << bits);
+        let mut frames = Vec::new();
+
+        // Reads a run of sub-blocks, ending with an empty one.
+        let sub_blocks = |i: &mut usize| {
+            let mut data = Vec::new();
+            while gif[*i] != 0 {
+                let len = gif[*i] as usize;
+                data.extend_from_slice(&gif[*i + 1..*i + 1 + len]);
+                *i += len + 1;
             }
-        })
-    });
-}
+            *i += 1;
+            data
+        };
 
-fn simulate_step(grid: &mut [Vec<u8>]) -> u64 {
-    increase_energy_levels(grid);
+        loop {
+            match gif[i] {
+                0x21 => {
+                    i += 2;
+                    sub_blocks(&mut i);
+                }
+                0x2c => {
+                    let min_code_size = gif[i + 10];
+                    i += 11;
+                    frames.push(lzw_decompress(min_code_size, &sub_blocks(&mut i)));
+                }
+                0x3b => return frames,
+                other => panic!("Unexpected block {:#x}", other),
+            }
+        }
+    }
 
-    let num_flashed = flash(grid);
+    fn lzw_decompress(min_code_size: u8, data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let reset = || (0..clear).map(|i| vec![i as u8]).chain([vec![], vec![]]).collect::<Vec<Vec<u8>>>();
 
-    reset_flashed(grid);
+        let mut table = reset();
+        let mut code_size = min_code_size as u32 + 1;
+        let mut previous: Option<usize> = None;
+        let mut pixels = Vec::new();
+        let mut bit = 0;
 
-    num_flashed
-}
+        loop {
+            let code = (0..code_size).fold(0, |code, b| {
+                let at = bit + b as usize;
+                code | (((data[at / 8] >> (at % 8)) & 1) as usize) << b
+            });
+            bit += code_size as usize;
 
-pub fn solve_pt1(filename: &str) -> u64 {
-    let mut grid = parse(filename);
+            if code == clear {
+                table = reset();
+                code_size = min_code_size as u32 + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return pixels;
+            }
 
-    (0..100).map(|_| simulate_step(&mut grid)).sum()
-}
+            let entry = match previous {
+                None => table[code].clone(),
+                Some(previous) => {
+                    let entry = if code < table.len() {
+                        table[code].clone()
+                    } else {
+                        assert_eq!(code, table.len(), "Code {} isn't in the table yet", code);
+                        let mut entry = table[previous].clone();
+                        entry.push(entry[0]);
+                        entry
+                    };
+                    let mut added = table[previous].clone();
+                    added.push(entry[0]);
+                    if table.len() < 4096 {
+                        table.push(added);
+                    }
+                    if table.len() == 1 << code_size && code_size < 12 {
+                        code_size += 1;
+                    }
+                    entry
+                }
+            };
+            pixels.extend_from_slice(&entry);
+            previous = Some(code);
+        }
+    }
 
-pub fn solve_pt2(filename: &str) -> u64 {
-    let mut grid = parse(filename);
-    let size = (grid.len() * grid[0].len()) as u64;
+    #[test]
+    fn test_gif_encoding() {
+        // The sample image from "What's in a GIF", along with the image data it gives.
+        let rows = [
+            "1111122222",
+            "1111122222",
+            "1111122222",
+            "1110000222",
+            "1110000222",
+            "2220000111",
+            "2220000111",
+            "2222211111",
+            "2222211111",
+            "2222211111",
+        ];
+        let frames = vec![rows.iter().flat_map(|row| row.bytes().map(|b| b - b'0')).collect::<Vec<u8>>()];
+        let palette = [[255, 255, 255], [255, 0, 0], [0, 0, 255], [0, 0, 0]];
+        let gif = encode_gif(10, 10, &palette, &frames, 0);
+        let image_data = [
+            0x02, 0x16, 0x8c, 0x2d, 0x99, 0x87, 0x2a, 0x1c, 0xdc, 0x33, 0xa0, 0x02, 0x75, 0xec, 0x95, 0xfa, 0xa8, 0xde,
+            0x60, 0x8c, 0x04, 0x91, 0x4c, 0x01, 0x00, 0x3b,
+        ];
+        assert!(gif.ends_with(&image_data));
+        assert_eq!(decode_gif(&gif), frames);
 
-    (1..).map(|i| (i, simulate_step(&mut grid))).find(|(_, x)| *x == size).unwrap().0
-}
+        // Noisy enough to fill up the code table, so the codes have to grow to 12 bits and then start again.
+        let mut seed = 12345u32;
+        let noise: Vec<u8> = (0..200 * 200)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as u8 % 20
+            })
+            .collect();
+        let palette = [[0, 0, 0]; 20];
+        let frames = vec![noise, vec![7; 200 * 200]];
+        assert_eq!(decode_gif(&encode_gif(200, 200, &palette, &frames, 0)), frames);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_demo_waves() {
+        let frames = flash_frames("demo.txt", 100);
+        let steps = frames.iter().filter(|f| f.wave.is_none()).count();
+        assert_eq!(steps, 101);
+        // Every flash has to start somewhere.
+        assert!(frames.iter().filter(|f| f.wave.is_some()).all(|f| f.pixels.cells().contains(&Pixel::Fired(0))));
+    }
 
     #[test]
-    fn test_pt1() {
-        assert_eq!(solve_pt1("demo.txt"), 1656);
+    fn test_life_blinker() {
+        let vertical = Grid::from_fn(5, 5, |x, y| x == 2 && (1..=3).contains(&y));
+        let horizontal = Grid::from_fn(5, 5, |x, y| y == 2 && (1..=3).contains(&x));
+        let mut life = Automaton::new(Life, vertical.clone());
+
+        assert_eq!(life.step(), 0);
+        assert_eq!(life.grid, horizontal);
+        life.step();
+        assert_eq!(life.grid, vertical);
     }
 
     #[test]
-    fn test_pt2() {
-        assert_eq!(solve_pt2("demo.txt"), 195);
+    fn test_sandpile() {
+        let mut pile = Automaton::new(Sandpile::default(), Grid::from_fn(3, 3, |x, y| if (x, y) == (1, 1) { 16 } else { 0 }));
+
+        // The middle topples twice, giving each neighbour 2 grains.
+        assert_eq!(pile.step(), 2);
+        assert_eq!(pile.grid, Grid::from_fn(3, 3, |x, y| if (x, y) == (1, 1) { 0 } else { 2 }));
+
+        // It's stable now, so nothing else happens.
+        assert_eq!(pile.step(), 0);
     }
 }
@@ -0,0 +1,15 @@
+use crate::automaton::Rule;
+
+/// Conway's Game of Life. A live cell stays alive with 2 or 3 live neighbours, and a dead cell comes alive with
+/// exactly 3.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Life;
+
+impl Rule for Life {
+    type Cell = bool;
+
+    fn update<'a>(&self, cell: &bool, neighbours: impl Iterator<Item = &'a bool>) -> bool {
+        let alive = neighbours.filter(|&&n| n).count();
+        matches!((cell, alive), (true, 2) | (_, 3))
+    }
+}
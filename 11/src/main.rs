@@ -1,17 +1,57 @@
 use advent2021_11::*;
-use std::env;
+use std::{env, fs};
+
+fn usage(program: &str) -> ! {
+    panic!(
+        "Usage: {} [--predict <steps>] [--replay | --gif <output file>] [--steps <steps>] [--fps <fps>] <filename>",
+        program
+    );
+}
 
 fn main() {
     // Get the path to the file to read
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Usage: {} <filename>", args[0]);
+
+    let mut filename = None;
+    let mut predict = None;
+    let mut replay_mode = false;
+    let mut gif_file = None;
+    let mut steps = 100;
+    let mut fps = 10.0;
+
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--predict" => predict = rest.next().and_then(|s| s.parse::<u64>().ok()).or_else(|| usage(&args[0])),
+            "--replay" => replay_mode = true,
+            "--gif" => gif_file = Some(rest.next().unwrap_or_else(|| usage(&args[0]))),
+            "--steps" => steps = rest.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| usage(&args[0])),
+            "--fps" => fps = rest.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| usage(&args[0])),
+            _ if filename.is_none() => filename = Some(arg),
+            _ => usage(&args[0]),
+        }
+    }
+    let filename = filename.unwrap_or_else(|| usage(&args[0]));
+
+    if replay_mode {
+        replay(&flash_frames(filename, steps), fps);
+        return;
+    }
+    if let Some(gif_file) = gif_file {
+        let gif = frames_to_gif(&flash_frames(filename, steps), 8, fps);
+        fs::write(gif_file, gif).expect("Something went wrong writing the GIF");
+        return;
     }
-    let filename = &args[1];
 
     let result = solve_pt1(filename);
     println!("Part 1: {result}");
 
     let result = solve_pt2(filename);
     println!("Part 2: {result}");
+
+    if let Some(steps) = predict {
+        let (cycle, flashes) = predict_flashes(filename, steps);
+        println!("Repeats every {} steps from step {}", cycle.period, cycle.start);
+        println!("Flashes after {steps} steps: {flashes}");
+    }
 }
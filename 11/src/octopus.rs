@@ -0,0 +1,71 @@
+use crate::automaton::Rule;
+
+/// An octopus' energy level, and whether it's flashed yet this step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OctopusCell {
+    pub energy: u32,
+    pub flashed: bool,
+}
+
+impl OctopusCell {
+    pub fn new(energy: u32) -> Self {
+        Self { energy, flashed: false }
+    }
+}
+
+/// The octopuses from the puzzle. Each step their energy goes up by one, and any that reach the threshold flash,
+/// giving energy to all their neighbours. An octopus can only flash once a step, and its energy goes back to the reset
+/// value afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Octopus {
+    pub threshold: u32,
+    pub reset: u32,
+}
+
+impl Octopus {
+    pub fn new(threshold: u32, reset: u32) -> Self {
+        Self { threshold, reset }
+    }
+
+    /// How close an octopus is to flashing, from 0 up to `shades - 1`.
+    pub fn shade(&self, cell: &OctopusCell, shades: usize) -> usize {
+        let energy = cell.energy.min(self.threshold) as usize;
+        (energy * shades / self.threshold.max(1) as usize).min(shades - 1)
+    }
+}
+
+impl Default for Octopus {
+    /// Flash once the energy gets above 9, and go back to 0, like in the puzzle.
+    fn default() -> Self {
+        Self::new(10, 0)
+    }
+}
+
+impl Rule for Octopus {
+    type Cell = OctopusCell;
+
+    fn update<'a>(&self, cell: &OctopusCell, _neighbours: impl Iterator<Item = &'a OctopusCell>) -> OctopusCell {
+        OctopusCell::new(cell.energy.saturating_add(1))
+    }
+
+    fn should_fire(&self, cell: &OctopusCell) -> bool {
+        cell.energy >= self.threshold && !cell.flashed
+    }
+
+    fn fire(&self, cell: &mut OctopusCell) {
+        cell.flashed = true;
+    }
+
+    fn receive(&self, cell: &mut OctopusCell) {
+        // It's going to be reset anyway if it's already flashed.
+        if !cell.flashed {
+            cell.energy = cell.energy.saturating_add(1);
+        }
+    }
+
+    fn finish(&self, cell: &mut OctopusCell) {
+        if cell.flashed {
+            *cell = OctopusCell::new(self.reset);
+        }
+    }
+}
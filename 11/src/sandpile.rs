@@ -0,0 +1,49 @@
+use crate::automaton::Rule;
+use crate::topology::Topology;
+
+/// An abelian sandpile. Any cell holding at least `capacity` grains topples, sending one grain to each neighbour.
+/// Grains that would go off the edge of the grid are lost.
+///
+/// Nothing changes at the start of a step, so a step just keeps toppling cells until the pile is stable again.
+#[derive(Debug, Clone, Copy)]
+pub struct Sandpile {
+    pub capacity: u32,
+}
+
+impl Sandpile {
+    pub fn new(capacity: u32) -> Self {
+        Self { capacity }
+    }
+
+    /// A sandpile where cells topple once they have a grain for each of their neighbours.
+    pub fn for_topology(topology: &Topology) -> Self {
+        Self::new(topology.max_neighbours() as u32)
+    }
+}
+
+impl Default for Sandpile {
+    /// One grain for each of the 8 neighbours.
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl Rule for Sandpile {
+    type Cell = u32;
+
+    fn update<'a>(&self, cell: &u32, _neighbours: impl Iterator<Item = &'a u32>) -> u32 {
+        *cell
+    }
+
+    fn should_fire(&self, cell: &u32) -> bool {
+        *cell >= self.capacity
+    }
+
+    fn fire(&self, cell: &mut u32) {
+        *cell -= self.capacity;
+    }
+
+    fn receive(&self, cell: &mut u32) {
+        *cell += 1;
+    }
+}
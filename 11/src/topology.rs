@@ -0,0 +1,112 @@
+const MOORE: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+const VON_NEUMANN: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+// Hex grids use "odd-r" offset coordinates, where every odd row is pushed half a cell to the right. That means the
+// cells above and below are in different columns depending on which row we're on.
+const HEX_EVEN_ROW: [(i32, i32); 6] = [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+const HEX_ODD_ROW: [(i32, i32); 6] = [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+/// Which cells count as being next to each other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Neighbourhood {
+    /// All 8 surrounding cells, including diagonals.
+    #[default]
+    Moore,
+    /// Just the 4 cells above, below, left and right.
+    VonNeumann,
+    /// The 6 cells around a hexagon, with odd rows pushed half a cell to the right.
+    Hex,
+    /// Any set of offsets from the cell, as `(dx, dy)`.
+    Custom(Vec<(i32, i32)>),
+}
+
+/// What happens at the edges of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Boundary {
+    /// Cells on the edge just have fewer neighbours.
+    #[default]
+    Clip,
+    /// The grid wraps around like a torus, so the left edge is next to the right edge, and the top is next to the
+    /// bottom.
+    ///
+    /// On a hex grid the height should be even, otherwise the top and bottom rows won't line up. On very small grids a
+    /// cell can end up being next to the same cell more than once, or even to itself.
+    Wrap,
+}
+
+/// The shape of the grid: which cells are next to each other, and what happens at the edges.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Topology {
+    pub neighbourhood: Neighbourhood,
+    pub boundary: Boundary,
+}
+
+impl Topology {
+    pub fn new(neighbourhood: Neighbourhood, boundary: Boundary) -> Self {
+        Self { neighbourhood, boundary }
+    }
+
+    /// 8 neighbours, clipped at the edges. This is what the puzzle uses.
+    pub fn moore() -> Self {
+        Self::new(Neighbourhood::Moore, Boundary::Clip)
+    }
+
+    pub fn von_neumann() -> Self {
+        Self::new(Neighbourhood::VonNeumann, Boundary::Clip)
+    }
+
+    pub fn hex() -> Self {
+        Self::new(Neighbourhood::Hex, Boundary::Clip)
+    }
+
+    pub fn custom(deltas: Vec<(i32, i32)>) -> Self {
+        Self::new(Neighbourhood::Custom(deltas), Boundary::Clip)
+    }
+
+    /// The same neighbourhood, but wrapping around at the edges.
+    pub fn wrapped(self) -> Self {
+        Self { boundary: Boundary::Wrap, ..self }
+    }
+
+    /// The most neighbours any cell can have, which is what cells away from the edges get.
+    pub fn max_neighbours(&self) -> usize {
+        self.deltas(0).len()
+    }
+
+    fn deltas(&self, y: usize) -> &[(i32, i32)] {
+        match &self.neighbourhood {
+            Neighbourhood::Moore => &MOORE,
+            Neighbourhood::VonNeumann => &VON_NEUMANN,
+            Neighbourhood::Hex if y.is_multiple_of(2) => &HEX_EVEN_ROW,
+            Neighbourhood::Hex => &HEX_ODD_ROW,
+            Neighbourhood::Custom(deltas) => deltas,
+        }
+    }
+
+    /// Gets the positions of the cells next to a cell, in a grid of the given size.
+    pub fn neighbours(&self, width: usize, height: usize, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (width, height) = (width as i32, height as i32);
+        let boundary = self.boundary;
+
+        self.deltas(y).iter().filter_map(move |(dx, dy)| {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+
+            match boundary {
+                Boundary::Clip if nx < 0 || ny < 0 || nx >= width || ny >= height => None,
+                Boundary::Clip => Some((nx as usize, ny as usize)),
+                Boundary::Wrap => Some((nx.rem_euclid(width) as usize, ny.rem_euclid(height) as usize)),
+            }
+        })
+    }
+}
@@ -0,0 +1,34 @@
+//! Compares the memoised path counting against trying every path, on a made up 30 cave system.
+//!
+//! Run with `cargo bench -p advent2021_12`.
+
+use advent2021_12::{generate_caves, Graph, RevisitPolicy, Route};
+use std::time::Instant;
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed().as_secs_f64() * 1000.0)
+}
+
+fn main() {
+    let input = generate_caves(30, 3);
+    let graph: Graph = input.parse().unwrap();
+
+    for (description, policy) in [
+        ("No revisiting", RevisitPolicy::part1()),
+        ("Revisiting one small cave", RevisitPolicy::part2()),
+    ] {
+        let (memoised, memoised_ms) = time(|| graph.count_paths(Route::default(), &policy));
+        let (brute_force, brute_force_ms) = time(|| graph.number_of_paths_brute_force(Route::default(), &policy));
+        assert_eq!(memoised, brute_force);
+
+        println!(
+            "{}: {} paths, memoised {:.2}ms, brute force {:.2}ms",
+            description,
+            memoised.unwrap(),
+            memoised_ms,
+            brute_force_ms
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::error::GraphError;
+use crate::graph::{Graph, Route};
+use crate::policy::{RevisitPolicy, Visit};
+
+/// The caves with their names swapped for numbers, so they can be looked up without hashing strings.
+pub struct CaveIds {
+    pub names: Vec<String>,
+    pub neighbors: Vec<Vec<usize>>,
+    /// The cost of the edge to each neighbor, in the same order.
+    pub costs: Vec<Vec<u64>>,
+    pub is_big: Vec<bool>,
+}
+
+impl CaveIds {
+    pub fn new(graph: &Graph) -> Self {
+        // Sort the names so the ids don't depend on the order of the hash map.
+        let mut names: Vec<String> = graph.nodes.keys().cloned().collect();
+        names.sort();
+        let ids: HashMap<&str, usize> = names.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+
+        let neighbors = names
+            .iter()
+            .map(|name| graph.nodes[name].neighbors.iter().map(|n| ids[n.as_str()]).collect())
+            .collect();
+        let costs = names.iter().map(|name| graph.nodes[name].costs.clone()).collect();
+        let is_big = names.iter().map(|name| graph.nodes[name].is_big).collect();
+
+        Self { names, neighbors, costs, is_big }
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names.binary_search_by(|n| n.as_str().cmp(name)).ok()
+    }
+}
+
+/// A revisit policy worked out for a particular set of caves.
+///
+/// The number of times each cave with a limit has been visited is packed into a u128, a few bits per cave. For the
+/// puzzle's rules that's just one bit per small cave for part 1, or two for part 2.
+pub struct VisitLimits<'a> {
+    caves: &'a CaveIds,
+    policy: &'a RevisitPolicy,
+    pub start: usize,
+    pub end: Option<usize>,
+    /// Where each cave's visit count starts in the packed counts, or None if it can be visited any number of times.
+    offsets: Vec<Option<u32>>,
+    bits_per_cave: u32,
+    forbidden: HashSet<(usize, usize)>,
+}
+
+impl<'a> VisitLimits<'a> {
+    pub fn new(caves: &'a CaveIds, route: Route, policy: &'a RevisitPolicy) -> Result<Self, GraphError> {
+        let start = caves.id(route.from).ok_or_else(|| GraphError::UnknownCave(route.from.to_string()))?;
+
+        let max_visits: Vec<Option<usize>> = caves
+            .names
+            .iter()
+            .zip(&caves.is_big)
+            .map(|(name, &is_big)| policy.max_visits(name, is_big))
+            .collect();
+        let most_visits = max_visits.iter().flatten().copied().max().unwrap_or(0);
+        let bits_per_cave = usize::BITS - most_visits.leading_zeros();
+
+        let mut next_offset = 0;
+        let mut offsets = Vec::new();
+        for max in &max_visits {
+            offsets.push(max.map(|_| next_offset));
+            if max.is_some() {
+                next_offset += bits_per_cave;
+            }
+        }
+        if next_offset > u128::BITS {
+            return Err(GraphError::TooManyLimits { bits: next_offset });
+        }
+
+        let forbidden = policy
+            .forbidden_edges
+            .iter()
+            .filter_map(|(a, b)| Some((caves.id(a)?, caves.id(b)?)))
+            .flat_map(|(a, b)| [(a, b), (b, a)])
+            .collect();
+
+        Ok(Self {
+            caves,
+            policy,
+            start,
+            // There's just no paths if there's no end.
+            end: caves.id(route.to),
+            offsets,
+            bits_per_cave,
+            forbidden,
+        })
+    }
+
+    /// Goes into a cave, given the packed visit counts and how many caves have gone over their usual limit so far.
+    /// Returns the new counts and number of extras, or None if the policy doesn't allow it.
+    pub fn visit(&self, cave: usize, visits: u128, extras_used: usize) -> Option<(u128, usize)> {
+        let Some(offset) = self.offsets[cave] else {
+            return Some((visits, extras_used));
+        };
+
+        let mask = (1 << self.bits_per_cave) - 1;
+        let count = ((visits >> offset) & mask) as usize;
+        let extras_used = match self.policy.check_visit(&self.caves.names[cave], self.caves.is_big[cave], count, extras_used) {
+            Visit::Allowed => extras_used,
+            Visit::UsesExtra => extras_used + 1,
+            Visit::NotAllowed => return None,
+        };
+
+        Some((visits + (1 << offset), extras_used))
+    }
+
+    /// Whether a path can go straight from one cave to another. Paths can't go back to the start.
+    pub fn can_move(&self, from: usize, to: usize) -> bool {
+        to != self.start && !self.forbidden.contains(&(from, to))
+    }
+}
+
+/// How many paths there are along a route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathCount {
+    Finite(u64),
+    /// Paths can go back and forth between these two caves as many times as they like.
+    Infinite { between: (String, String) },
+}
+
+impl PathCount {
+    /// Infinitely many paths, going round a loop through these two caves.
+    pub fn infinite(caves: &CaveIds, (a, b): (usize, usize)) -> Self {
+        PathCount::Infinite { between: (caves.names[a].clone(), caves.names[b].clone()) }
+    }
+}
+
+impl fmt::Display for PathCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathCount::Finite(count) => write!(f, "{}", count),
+            PathCount::Infinite { between: (a, b) } => write!(f, "infinitely many, going back and forth between {} and {}", a, b),
+        }
+    }
+}
+
+/// Counts the paths along a route that follow a revisit policy.
+///
+/// Once we know how many times each cave has been visited, the number of ways to get from a cave to the end doesn't
+/// depend on how we got there, so each of those only needs working out once.
+///
+/// Going round a loop of caves that can be visited any number of times gets back to the same cave with the same visit
+/// counts. If a path can do that and still get to the end, it can go round as many times as it likes.
+pub struct PathCounter<'a> {
+    caves: &'a CaveIds,
+    limits: VisitLimits<'a>,
+    memo: HashMap<(usize, u128), u64>,
+    /// The caves and visit counts we're still working out the number of paths for.
+    in_progress: HashSet<(usize, u128)>,
+    /// The ones of those a loop has come back to, and the cave it came back from.
+    looped: HashMap<(usize, u128), usize>,
+    /// A loop that can get to the end.
+    endless: Option<(usize, usize)>,
+}
+
+impl<'a> PathCounter<'a> {
+    pub fn new(caves: &'a CaveIds, route: Route, policy: &'a RevisitPolicy) -> Result<Self, GraphError> {
+        Ok(Self {
+            caves,
+            limits: VisitLimits::new(caves, route, policy)?,
+            memo: HashMap::new(),
+            in_progress: HashSet::new(),
+            looped: HashMap::new(),
+            endless: None,
+        })
+    }
+
+    /// Counts the paths, or says there's infinitely many if a path can go round in a loop forever.
+    pub fn count(&mut self) -> PathCount {
+        let start = self.limits.start;
+        let count = self.count_from(start, 0, 0, start);
+        match self.endless {
+            Some(between) => PathCount::infinite(self.caves, between),
+            None => PathCount::Finite(count),
+        }
+    }
+
+    /// Counts the paths to the end from `cave`, where `visits` holds the packed visit counts from before we got to it,
+    /// and `from` is the cave we came from.
+    ///
+    /// `extras_used` is how many caves have gone over their usual limit. It's not part of the memo key, because it can
+    /// be worked out from the visit counts.
+    ///
+    /// Going round a loop doesn't count as a path here. The count only comes out right if there aren't any loops that
+    /// can get to the end, and `count` checks for those.
+    fn count_from(&mut self, cave: usize, visits: u128, extras_used: usize, from: usize) -> u64 {
+        if Some(cave) == self.limits.end {
+            return 1;
+        }
+
+        let key = (cave, visits);
+        if let Some(&count) = self.memo.get(&key) {
+            return count;
+        }
+        if self.in_progress.contains(&key) {
+            self.looped.insert(key, from);
+            return 0;
+        }
+
+        let Some((visits, extras_used)) = self.limits.visit(cave, visits, extras_used) else {
+            return 0;
+        };
+
+        self.in_progress.insert(key);
+        let caves = self.caves;
+        let mut count = 0;
+        for &n in &caves.neighbors[cave] {
+            if self.limits.can_move(cave, n) {
+                count += self.count_from(n, visits, extras_used, cave);
+            }
+        }
+        self.in_progress.remove(&key);
+
+        // A loop came back here, and there's a way on to the end, so the path can go round it forever first.
+        if let Some(back_from) = self.looped.remove(&key) {
+            if count > 0 && self.endless.is_none() {
+                self.endless = Some((cave, back_from));
+            }
+        }
+
+        self.memo.insert(key, count);
+        count
+    }
+}
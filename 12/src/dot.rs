@@ -0,0 +1,343 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::error::GraphError;
+use crate::graph::Graph;
+
+/// A piece of a dot file.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A name, number or quoted string. Keywords like `graph` come out as these too.
+    Id(String),
+    /// `--` or `->`. Caves are always connected both ways, so these mean the same thing.
+    EdgeOp,
+    Punct(char),
+}
+
+/// Splits a dot file into tokens, along with the line each one is on. Comments are skipped.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, GraphError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                line += 1;
+                line_start = true;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            // Lines starting with # are preprocessor output, which get ignored.
+            '#' if line_start => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            prev = c;
+                        }
+                        None => return Err(dot_error(line, "comment is never closed".to_string())),
+                    }
+                }
+            }
+            '-' if chars.next_if(|&c| c == '-' || c == '>').is_some() => tokens.push((Token::EdgeOp, line)),
+            '"' => {
+                let start = line;
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Escaped quotes are the only escape dot has. Anything else keeps its backslash.
+                        Some('\\') if chars.next_if_eq(&'"').is_some() => s.push('"'),
+                        // A backslash at the end of a line joins it onto the next one.
+                        Some('\\') if chars.next_if_eq(&'\n').is_some() => line += 1,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            s.push(c);
+                        }
+                        None => return Err(dot_error(start, "string is never closed".to_string())),
+                    }
+                }
+                tokens.push((Token::Id(s), start));
+            }
+            '<' => {
+                // HTML strings can nest angle brackets. We just keep the text inside.
+                let start = line;
+                let mut depth = 1;
+                let mut s = String::new();
+                loop {
+                    let Some(c) = chars.next() else {
+                        return Err(dot_error(start, "HTML string is never closed".to_string()));
+                    };
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        '\n' => line += 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    s.push(c);
+                }
+                tokens.push((Token::Id(s), start));
+            }
+            '{' | '}' | '[' | ']' | ';' | ',' | '=' | ':' => tokens.push((Token::Punct(c), line)),
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut s = c.to_string();
+                while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_' || c == '.') {
+                    s.push(c);
+                }
+                tokens.push((Token::Id(s), line));
+            }
+            c => return Err(dot_error(line, format!("unexpected character `{}`", c))),
+        }
+        line_start = false;
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a graph from a dot file, like the ones graphviz uses.
+///
+/// This understands the parts of dot that matter for caves: node and edge statements, edge chains like `a -- b -- c`,
+/// subgraphs, and attribute lists. Edges are always connected both ways, even in a digraph. An edge's `weight`
+/// attribute is used as its cost, and caves are classified with `is_big`. Everything else is skipped over.
+///
+/// In a `strict` graph, edges that appear more than once are only added once, whichever way round they are.
+pub fn parse_dot(input: &str, is_big: impl Fn(&str) -> bool) -> Result<Graph, GraphError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        graph: Graph::new(),
+        strict: false,
+        seen_edges: HashSet::new(),
+    };
+
+    parser.parse_graph()?;
+    let mut graph = parser.graph;
+    for node in graph.nodes.values_mut() {
+        node.is_big = is_big(&node.name);
+    }
+
+    Ok(graph)
+}
+
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    pos: usize,
+    graph: Graph,
+    strict: bool,
+    seen_edges: HashSet<(String, String)>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(s)) if s.eq_ignore_ascii_case(keyword))
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |&(_, line)| line)
+    }
+
+    fn error(&self, expected: &str) -> GraphError {
+        let found = self.peek().map_or("the end of the file".to_string(), describe);
+        dot_error(self.line(), format!("expected {}, found {}", expected, found))
+    }
+
+    fn next_if_punct(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), GraphError> {
+        if self.next_if_punct(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{}`", c)))
+        }
+    }
+
+    fn expect_id(&mut self) -> Result<String, GraphError> {
+        match self.peek() {
+            Some(Token::Id(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => Err(self.error("a name")),
+        }
+    }
+
+    fn parse_graph(&mut self) -> Result<(), GraphError> {
+        if self.peek_keyword("strict") {
+            self.strict = true;
+            self.pos += 1;
+        }
+        if !self.peek_keyword("graph") && !self.peek_keyword("digraph") {
+            return Err(self.error("`graph` or `digraph`"));
+        }
+        self.pos += 1;
+
+        if matches!(self.peek(), Some(Token::Id(_))) {
+            self.pos += 1;
+        }
+        self.parse_block(&HashMap::new())?;
+
+        if self.peek().is_some() {
+            return Err(self.error("the end of the file"));
+        }
+        Ok(())
+    }
+
+    /// Parses a `{ ... }` block of statements. `edge_defaults` are the edge attributes set by the blocks outside it.
+    fn parse_block(&mut self, edge_defaults: &HashMap<String, String>) -> Result<(), GraphError> {
+        self.expect_punct('{')?;
+        let mut edge_defaults = edge_defaults.clone();
+
+        while !self.next_if_punct('}') {
+            if self.peek().is_none() {
+                return Err(self.error("`}`"));
+            }
+            self.parse_statement(&mut edge_defaults)?;
+            self.next_if_punct(';');
+        }
+
+        Ok(())
+    }
+
+    fn parse_statement(&mut self, edge_defaults: &mut HashMap<String, String>) -> Result<(), GraphError> {
+        if self.peek_keyword("subgraph") || self.peek() == Some(&Token::Punct('{')) {
+            if self.peek_keyword("subgraph") {
+                self.pos += 1;
+                if matches!(self.peek(), Some(Token::Id(_))) {
+                    self.pos += 1;
+                }
+            }
+            return self.parse_block(edge_defaults);
+        }
+
+        if self.peek_keyword("graph") || self.peek_keyword("node") || self.peek_keyword("edge") {
+            let is_edge = self.peek_keyword("edge");
+            self.pos += 1;
+            let attributes = self.parse_attributes()?;
+            if is_edge {
+                edge_defaults.extend(attributes);
+            }
+            return Ok(());
+        }
+
+        let mut nodes = vec![self.parse_node_id()?];
+
+        // A graph attribute like `rankdir = LR`.
+        if nodes.len() == 1 && self.next_if_punct('=') {
+            self.expect_id()?;
+            return Ok(());
+        }
+
+        while self.peek() == Some(&Token::EdgeOp) {
+            self.pos += 1;
+            nodes.push(self.parse_node_id()?);
+        }
+
+        let mut attributes = edge_defaults.clone();
+        attributes.extend(self.parse_attributes()?);
+
+        if nodes.len() == 1 {
+            self.add_node(&nodes[0]);
+            return Ok(());
+        }
+
+        let cost = match attributes.get("weight") {
+            Some(weight) => weight
+                .parse()
+                .map_err(|_| dot_error(self.line(), format!("edge weight `{}` isn't a whole number", weight)))?,
+            None => 1,
+        };
+        for pair in nodes.windows(2) {
+            self.add_edge(&pair[0], &pair[1], cost);
+        }
+
+        Ok(())
+    }
+
+    /// Parses a node name, skipping over any port after it like `a:n`.
+    fn parse_node_id(&mut self) -> Result<String, GraphError> {
+        let id = self.expect_id()?;
+        while self.next_if_punct(':') {
+            self.expect_id()?;
+        }
+        Ok(id)
+    }
+
+    /// Parses any number of `[a=b, c=d]` lists.
+    fn parse_attributes(&mut self) -> Result<HashMap<String, String>, GraphError> {
+        let mut attributes = HashMap::new();
+
+        while self.next_if_punct('[') {
+            while !self.next_if_punct(']') {
+                let name = self.expect_id()?;
+                self.expect_punct('=')?;
+                let value = self.expect_id()?;
+                attributes.insert(name, value);
+                if !self.next_if_punct(',') {
+                    self.next_if_punct(';');
+                }
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    fn add_node(&mut self, name: &str) {
+        if !self.graph.nodes.contains_key(name) {
+            self.graph.add_cave(name);
+        }
+    }
+
+    fn add_edge(&mut self, a: &str, b: &str, cost: u64) {
+        if self.strict {
+            let key = if a <= b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) };
+            if !self.seen_edges.insert(key) {
+                return;
+            }
+        }
+        self.graph.connect(a, b, cost);
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Id(s) => format!("`{}`", s),
+        Token::EdgeOp => "an edge".to_string(),
+        Token::Punct(c) => format!("`{}`", c),
+    }
+}
+
+fn dot_error(line: usize, message: String) -> GraphError {
+    GraphError::Dot { line, message }
+}
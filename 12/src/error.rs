@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+
+/// Everything that can go wrong reading caves or finding paths through them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    /// A line that isn't an edge like "a-b", or a size annotation.
+    BadEdge { line: usize, text: String },
+    /// An edge with a cost that isn't a whole number, like "a-b:lots".
+    BadCost { line: usize, cost: String },
+    /// An edge from a cave to itself.
+    SelfLoop { line: usize, cave: String },
+    /// An edge between two caves that are already connected, either way round.
+    DuplicateEdge { line: usize, a: String, b: String },
+    /// Something wrong with a dot file.
+    Dot { line: usize, message: String },
+    /// A route that starts at a cave that isn't in the graph.
+    UnknownCave(String),
+    /// There's no way at all between the two caves, whatever the rules about visiting them.
+    NoRoute { from: String, to: String },
+    /// The visit counts need more bits than can be packed into a u128.
+    TooManyLimits { bits: u32 },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::BadEdge { line, text } => write!(f, "line {}: expected an edge like \"a-b\", found \"{}\"", line, text),
+            GraphError::BadCost { line, cost } => write!(f, "line {}: edge cost \"{}\" isn't a whole number", line, cost),
+            GraphError::SelfLoop { line, cave } => write!(f, "line {}: edge goes from {} back to itself", line, cave),
+            GraphError::DuplicateEdge { line, a, b } => write!(f, "line {}: {} and {} are already connected", line, a, b),
+            GraphError::Dot { line, message } => write!(f, "line {}: {}", line, message),
+            GraphError::UnknownCave(name) => write!(f, "there's no cave called `{}`", name),
+            GraphError::NoRoute { from, to } => write!(f, "there's no way to get from {} to {}", from, to),
+            GraphError::TooManyLimits { bits } => write!(
+                f,
+                "Too many caves with visit limits to count paths: needs {} bits, but the most we can do is {}",
+                bits,
+                u128::BITS
+            ),
+        }
+    }
+}
+
+impl Error for GraphError {}
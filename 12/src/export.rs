@@ -0,0 +1,134 @@
+use crate::graph::{Graph, Node};
+
+/// The caves sorted by name, so the output doesn't depend on the order of the hash map.
+fn sorted_nodes(graph: &Graph) -> Vec<&Node> {
+    let mut nodes: Vec<&Node> = graph.nodes.values().collect();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    nodes
+}
+
+fn dot_quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\\\""))
+}
+
+pub fn to_dot(graph: &Graph, highlight: &[&str]) -> String {
+    let highlighted_edge = |a: &str, b: &str| highlight.windows(2).any(|w| (w[0] == a && w[1] == b) || (w[0] == b && w[1] == a));
+
+    let mut dot = String::new();
+    dot.push_str("graph G {\n");
+    dot.push_str("  node [shape=ellipse];\n");
+
+    for node in sorted_nodes(graph) {
+        let mut attributes = Vec::new();
+        if node.is_big {
+            attributes.push("shape=box, style=bold");
+        }
+        if highlight.contains(&node.name.as_str()) {
+            attributes.push("color=red, fontcolor=red");
+        }
+
+        if attributes.is_empty() {
+            dot.push_str(&format!("  {};\n", dot_quote(&node.name)));
+        } else {
+            dot.push_str(&format!("  {} [{}];\n", dot_quote(&node.name), attributes.join(", ")));
+        }
+    }
+
+    for (a, b, cost) in graph.edges() {
+        let mut attributes = Vec::new();
+        if cost != 1 {
+            attributes.push(format!("label=\"{}\", weight={}", cost, cost));
+        }
+        if highlighted_edge(a, b) {
+            attributes.push("color=red, penwidth=2".to_string());
+        }
+
+        if attributes.is_empty() {
+            dot.push_str(&format!("  {} -- {};\n", dot_quote(a), dot_quote(b)));
+        } else {
+            dot.push_str(&format!("  {} -- {} [{}];\n", dot_quote(a), dot_quote(b), attributes.join(", ")));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn to_graphml(graph: &Graph) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    xml.push_str("  <key id=\"big\" for=\"node\" attr.name=\"big\" attr.type=\"boolean\"/>\n");
+    xml.push_str("  <key id=\"cost\" for=\"edge\" attr.name=\"cost\" attr.type=\"long\"/>\n");
+    xml.push_str("  <graph id=\"G\" edgedefault=\"undirected\">\n");
+
+    for node in sorted_nodes(graph) {
+        xml.push_str(&format!(
+            "    <node id=\"{}\"><data key=\"big\">{}</data></node>\n",
+            xml_escape(&node.name),
+            node.is_big
+        ));
+    }
+    for (a, b, cost) in graph.edges() {
+        xml.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"cost\">{}</data></edge>\n",
+            xml_escape(a),
+            xml_escape(b),
+            cost
+        ));
+    }
+
+    xml.push_str("  </graph>\n");
+    xml.push_str("</graphml>\n");
+    xml
+}
+
+fn json_quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Each cave maps to whether it's big and its neighbors, like
+/// `{"A": {"big": true, "neighbors": [{"cave": "b", "cost": 1}]}}`.
+pub fn to_json(graph: &Graph) -> String {
+    let caves: Vec<String> = sorted_nodes(graph)
+        .iter()
+        .map(|node| {
+            let neighbors: Vec<String> = node
+                .neighbors
+                .iter()
+                .zip(&node.costs)
+                .map(|(neighbor, cost)| format!("{{\"cave\": {}, \"cost\": {}}}", json_quote(neighbor), cost))
+                .collect();
+            format!(
+                "  {}: {{\"big\": {}, \"neighbors\": [{}]}}",
+                json_quote(&node.name),
+                node.is_big,
+                neighbors.join(", ")
+            )
+        })
+        .collect();
+
+    if caves.is_empty() {
+        "{}\n".to_string()
+    } else {
+        format!("{{\n{}\n}}\n", caves.join(",\n"))
+    }
+}
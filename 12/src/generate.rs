@@ -0,0 +1,54 @@
+/// Makes up a cave system for benchmarking, in the same format as the puzzle input.
+///
+/// There are `num_caves` caves including the start and end, about a quarter of them big. Big caves are never next to
+/// each other, otherwise there'd be infinitely many paths. The same seed always gives the same caves.
+pub fn generate_caves(num_caves: usize, seed: u64) -> String {
+    assert!(num_caves >= 4, "Need at least 4 caves for a start, an end, a big cave and a small cave");
+
+    // A simple xorshift generator, so we don't need any dependencies. The state can't be 0.
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let mut random = |n: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    };
+
+    let num_big = (num_caves / 4).max(1);
+    let num_small = num_caves - 2 - num_big;
+    let big: Vec<String> = (0..num_big).map(|i| cave_name(i).to_uppercase()).collect();
+    let small: Vec<String> = (0..num_small).map(cave_name).collect();
+
+    let mut edges = Vec::new();
+    for cave in &small {
+        // Every small cave is next to a big one, and some are next to another small cave too.
+        edges.push(format!("{}-{}", cave, big[random(num_big)]));
+        if random(3) == 0 {
+            let other = &small[random(num_small)];
+            // The other cave might already have picked this one.
+            if other != cave && !edges.contains(&format!("{}-{}", other, cave)) {
+                edges.push(format!("{}-{}", cave, other));
+            }
+        }
+    }
+    for end in ["start", "end"] {
+        edges.push(format!("{}-{}", end, big[random(num_big)]));
+        edges.push(format!("{}-{}", end, small[random(num_small)]));
+    }
+
+    edges.join("\n") + "\n"
+}
+
+/// Names caves "a", "b", ... "z", "ba", "bb" and so on.
+fn cave_name(mut i: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'a' + (i % 26) as u8);
+        i /= 26;
+        if i == 0 {
+            break;
+        }
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
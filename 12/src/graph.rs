@@ -1,9 +1,20 @@
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::counting::{CaveIds, PathCount, PathCounter, VisitLimits};
+use crate::dot;
+use crate::error::GraphError;
+use crate::export;
+use crate::paths::{PathFilter, Paths};
+use crate::policy::{RevisitPolicy, Visit};
+use crate::shortest::{self, WeightedPath};
 
 pub struct Node {
     pub name: String,
     // Names of the neighbors
     pub neighbors: Vec<String>,
+    // Cost of the edge to each neighbor, in the same order
+    pub costs: Vec<u64>,
     pub is_big: bool,
 }
 
@@ -12,15 +23,60 @@ impl Node {
         Node {
             name: name.to_string(),
             neighbors: Vec::new(),
-            is_big: name.chars().all(|c| c.is_uppercase()),
+            costs: Vec::new(),
+            is_big: is_uppercase(name),
         }
     }
 }
 
+/// How the puzzle decides which caves are big.
+pub fn is_uppercase(name: &str) -> bool {
+    name.chars().all(|c| c.is_uppercase())
+}
+
+/// Splits an edge like "a-b" into the two caves' names. If the names have dashes in, there have to be spaces round
+/// the dash between them, like "north-east - b".
+pub fn split_edge(edge: &str) -> Option<(&str, &str)> {
+    match edge.split_once(" - ") {
+        Some(names) => Some(names),
+        None if edge.matches('-').count() == 1 => edge.split_once('-'),
+        None => None,
+    }
+}
+
+/// Where paths start and finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+}
+
+impl<'a> Route<'a> {
+    pub fn new(from: &'a str, to: &'a str) -> Self {
+        Self { from, to }
+    }
+}
+
+impl Default for Route<'_> {
+    /// From "start" to "end", like in the puzzle.
+    fn default() -> Self {
+        Self::new("start", "end")
+    }
+}
+
+#[derive(Default)]
 pub struct Graph {
     pub nodes: HashMap<String, Node>,
 }
 
+impl FromStr for Graph {
+    type Err = GraphError;
+
+    fn from_str(input: &str) -> Result<Self, GraphError> {
+        Self::parse_with(input, is_uppercase)
+    }
+}
+
 impl Graph {
     pub fn new() -> Self {
         Graph {
@@ -28,97 +84,321 @@ impl Graph {
         }
     }
 
-    pub fn from_str(input: &str) -> Self {
+    /// Parses the input, using `is_big` to decide which caves are big rather than whether their names are uppercase.
+    ///
+    /// As well as edges, the input can have lines like "big: a b" or "small: C D" to say what size caves are, which
+    /// overrides `is_big`. These can go anywhere in the file. Blank lines are skipped.
+    pub fn parse_with(input: &str, is_big: impl Fn(&str) -> bool) -> Result<Self, GraphError> {
         let mut graph = Graph::new();
+        let mut sizes = HashMap::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            } else if let Some(names) = line.strip_prefix("big:") {
+                sizes.extend(names.split_whitespace().map(|name| (name, true)));
+            } else if let Some(names) = line.strip_prefix("small:") {
+                sizes.extend(names.split_whitespace().map(|name| (name, false)));
+            } else {
+                graph.add_edge(i + 1, line)?;
+            }
+        }
 
-        for line in input.lines() {
-            graph.add_edge(line);
+        for name in sizes.keys() {
+            graph.add_cave(name);
+        }
+        for node in graph.nodes.values_mut() {
+            node.is_big = sizes.get(node.name.as_str()).copied().unwrap_or_else(|| is_big(&node.name));
         }
 
-        graph
+        Ok(graph)
     }
 
     /// Parses a line from the graph input file and updates the graph to contain this edge, creating nodes if necessary.
+    /// `line_number` is only used for errors.
     ///
     /// Edges are bi-directional, and each line looks like this:
     /// "a-b"
-    pub fn add_edge(&mut self, line: &str) {
-        let mut parts = line.split('-');
-        let a = parts.next().unwrap();
-        let b = parts.next().unwrap();
+    ///
+    /// Edges can also have a cost, like "a-b:5". Without one, the cost is 1. If the names have dashes in, the edge
+    /// needs spaces round its dash, like "north-east - b".
+    ///
+    /// Edges from a cave to itself, or between caves that are already connected, are errors.
+    pub fn add_edge(&mut self, line_number: usize, line: &str) -> Result<(), GraphError> {
+        let bad_edge = || GraphError::BadEdge { line: line_number, text: line.to_string() };
+
+        let (edge, cost) = match line.split_once(':') {
+            Some((edge, cost)) => {
+                let cost = cost.trim();
+                let cost = cost.parse().map_err(|_| GraphError::BadCost { line: line_number, cost: cost.to_string() })?;
+                (edge, cost)
+            }
+            None => (line, 1),
+        };
+
+        let (a, b) = split_edge(edge).ok_or_else(bad_edge)?;
+        let (a, b) = (a.trim(), b.trim());
+        let is_name = |name: &str| !name.is_empty() && !name.contains(char::is_whitespace);
+        if !is_name(a) || !is_name(b) {
+            return Err(bad_edge());
+        }
+
+        if a == b {
+            return Err(GraphError::SelfLoop { line: line_number, cave: a.to_string() });
+        }
+        if self.nodes.get(a).is_some_and(|node| node.neighbors.iter().any(|n| n == b)) {
+            return Err(GraphError::DuplicateEdge { line: line_number, a: a.to_string(), b: b.to_string() });
+        }
+
+        self.connect(a, b, cost);
+        Ok(())
+    }
+
+    /// Checks there's some way to get along the route, ignoring the rules about how often caves can be visited. If
+    /// there isn't, there won't be any paths, which is probably a mistake in the input.
+    pub fn check_route(&self, route: Route) -> Result<(), GraphError> {
+        for name in [route.from, route.to] {
+            if !self.nodes.contains_key(name) {
+                return Err(GraphError::UnknownCave(name.to_string()));
+            }
+        }
+
+        let mut seen = HashSet::from([route.from]);
+        let mut to_visit = vec![route.from];
+        while let Some(name) = to_visit.pop() {
+            if name == route.to {
+                return Ok(());
+            }
+            for neighbor in &self.nodes[name].neighbors {
+                if seen.insert(neighbor) {
+                    to_visit.push(neighbor);
+                }
+            }
+        }
+
+        Err(GraphError::NoRoute { from: route.from.to_string(), to: route.to.to_string() })
+    }
+
+    /// Adds a cave with no edges, unless it's already there.
+    pub fn add_cave(&mut self, name: &str) -> &mut Node {
+        self.nodes.entry(name.to_string()).or_insert_with(|| Node::new(name))
+    }
 
-        let a_node = self.nodes.entry(a.to_string()).or_insert(Node::new(a));
+    /// Connects two caves both ways with an edge that costs `cost`, creating them if necessary.
+    pub fn connect(&mut self, a: &str, b: &str, cost: u64) {
+        let a_node = self.add_cave(a);
         a_node.neighbors.push(b.to_string());
+        a_node.costs.push(cost);
 
-        let b_node = self.nodes.entry(b.to_string()).or_insert(Node::new(b));
+        let b_node = self.add_cave(b);
         b_node.neighbors.push(a.to_string());
+        b_node.costs.push(cost);
+    }
+
+    /// Reads a graph from a dot file, deciding which caves are big by whether their names are uppercase.
+    pub fn from_dot(input: &str) -> Result<Self, GraphError> {
+        dot::parse_dot(input, is_uppercase)
     }
 
-    /// Outputs the graph as a dot file.
-    #[allow(dead_code)]
-    pub fn to_dot(&self) -> String {
-        let mut dot = String::new();
+    /// Reads a graph from a dot file, using `is_big` to decide which caves are big.
+    pub fn from_dot_with(input: &str, is_big: impl Fn(&str) -> bool) -> Result<Self, GraphError> {
+        dot::parse_dot(input, is_big)
+    }
 
-        dot.push_str("strict graph G {\n");
+    /// Every edge once, as `(a, b, cost)` with `a <= b`, sorted so the order doesn't depend on the hash map.
+    ///
+    /// Edges that were added more than once come out more than once.
+    pub fn edges(&self) -> Vec<(&str, &str, u64)> {
+        let mut edges = Vec::new();
 
         for node in self.nodes.values() {
-            for neighbor in &node.neighbors {
-                dot.push_str(&format!("  \"{}\" -- \"{}\";\n", node.name, neighbor));
+            let mut loops = 0;
+            for (neighbor, &cost) in node.neighbors.iter().zip(&node.costs) {
+                if node.name < *neighbor {
+                    edges.push((node.name.as_str(), neighbor.as_str(), cost));
+                } else if node.name == *neighbor {
+                    // An edge from a cave to itself is in its list of neighbors twice.
+                    if loops % 2 == 0 {
+                        edges.push((node.name.as_str(), neighbor.as_str(), cost));
+                    }
+                    loops += 1;
+                }
             }
         }
 
-        dot.push_str("}\n");
+        edges.sort();
+        edges
+    }
+
+    /// Outputs the graph as a dot file, with big caves drawn as boxes. Any caves in `highlight` and the edges between
+    /// them, in order, are drawn in red, so a path can be shown by passing its caves.
+    pub fn to_dot(&self, highlight: &[&str]) -> String {
+        export::to_dot(self, highlight)
+    }
+
+    /// Outputs the graph as GraphML, with whether each cave is big and the cost of each edge.
+    pub fn to_graphml(&self) -> String {
+        export::to_graphml(self)
+    }
+
+    /// Outputs the graph as JSON, with each cave's size and neighbors.
+    pub fn to_json(&self) -> String {
+        export::to_json(self)
+    }
+
+    /// Counts the paths along the route that follow the policy. If a path can go round a loop of caves that can be
+    /// visited any number of times and still get to the end after, it can go round forever, so there's infinitely many.
+    pub fn count_paths(&self, route: Route, policy: &RevisitPolicy) -> Result<PathCount, GraphError> {
+        let caves = CaveIds::new(self);
+        Ok(PathCounter::new(&caves, route, policy)?.count())
+    }
+
+    pub fn number_of_paths(&self) -> Result<PathCount, GraphError> {
+        self.count_paths(Route::default(), &RevisitPolicy::part1())
+    }
+
+    pub fn number_of_paths_with_revisiting(&self) -> Result<PathCount, GraphError> {
+        self.count_paths(Route::default(), &RevisitPolicy::part2())
+    }
+
+    /// Finds the cheapest path along the route, ignoring whether caves are big or small. Returns None if there isn't
+    /// one.
+    pub fn shortest_path(&self, route: Route) -> Result<Option<WeightedPath>, GraphError> {
+        let caves = CaveIds::new(self);
+        let start = caves.id(route.from).ok_or_else(|| GraphError::UnknownCave(route.from.to_string()))?;
+        Ok(caves.id(route.to).and_then(|end| shortest::shortest_path(&caves, start, end)))
+    }
+
+    /// Finds the `k` cheapest paths along the route that don't go through any cave twice, cheapest first. There might
+    /// be fewer than `k` of them.
+    pub fn k_shortest_paths(&self, route: Route, k: usize) -> Result<Vec<WeightedPath>, GraphError> {
+        let caves = CaveIds::new(self);
+        let start = caves.id(route.from).ok_or_else(|| GraphError::UnknownCave(route.from.to_string()))?;
+        Ok(match caves.id(route.to) {
+            Some(end) => shortest::k_shortest_paths(&caves, start, end, k),
+            None => Vec::new(),
+        })
+    }
+
+    /// Finds the cheapest path along the route that follows the policy. Returns None if there isn't one.
+    pub fn cheapest_path(&self, route: Route, policy: &RevisitPolicy) -> Result<Option<WeightedPath>, GraphError> {
+        let caves = CaveIds::new(self);
+        let limits = VisitLimits::new(&caves, route, policy)?;
+        Ok(shortest::cheapest_path(&caves, &limits))
+    }
 
-        dot
+    /// Counts the paths by trying every one of them. This is much slower than `count_paths`, but it's a useful check
+    /// that the two agree.
+    pub fn number_of_paths_brute_force(&self, route: Route, policy: &RevisitPolicy) -> Result<PathCount, GraphError> {
+        let mut search = BruteForce::default();
+        let count = self.number_of_paths_to_end(route.from, route, &HashMap::new(), 0, policy, &mut search)?;
+        Ok(match search.endless {
+            Some(between) => PathCount::Infinite { between },
+            None => PathCount::Finite(count),
+        })
     }
 
-    pub fn number_of_paths(&self) -> Result<u64, String> {
-        self.number_of_paths_to_end("start", &HashSet::new(), false)
+    /// If going into `name` from the end of `path` would just go round a loop, back to a cave the path was already in
+    /// with the same visit counts, gives where in the path that is. That only happens when `name` and every cave
+    /// since then can be visited any number of times.
+    pub fn loops_back<'b>(
+        &self,
+        path: impl DoubleEndedIterator<Item = &'b str> + ExactSizeIterator,
+        name: &str,
+        policy: &RevisitPolicy,
+    ) -> Option<usize> {
+        let unlimited = |name: &str| self.nodes.get(name).is_some_and(|node| policy.max_visits(name, node.is_big).is_none());
+        if !unlimited(name) {
+            return None;
+        }
+
+        for (i, cave) in path.enumerate().rev() {
+            if cave == name {
+                return Some(i);
+            }
+            if !unlimited(cave) {
+                return None;
+            }
+        }
+        None
     }
 
-    pub fn number_of_paths_with_revisiting(&self) -> Result<u64, String> {
-        self.number_of_paths_to_end("start", &HashSet::new(), true)
+    /// Lists the paths along the route that follow the policy and match the filter.
+    pub fn paths<'a>(&'a self, route: Route<'a>, policy: &'a RevisitPolicy, filter: PathFilter) -> Paths<'a> {
+        Paths::new(self, route, policy, filter)
     }
 
-    pub fn number_of_paths_to_end(
+    /// Counts the paths from a node to the end of the route, where `visits` is how many times each node has been
+    /// visited before getting here, and `extras_used` is how many small caves have gone over their usual limit.
+    ///
+    /// Paths can never go back to where they started, whatever the policy says. Going round a loop doesn't count as a
+    /// path, but if one can get to the end, `search` says so.
+    fn number_of_paths_to_end(
         &self,
         name: &str,
-        visited: &HashSet<String>,
-        mut can_revisit_small: bool
-    ) -> Result<u64, String> {
+        route: Route,
+        visits: &HashMap<String, usize>,
+        mut extras_used: usize,
+        policy: &RevisitPolicy,
+        search: &mut BruteForce,
+    ) -> Result<u64, GraphError> {
         // Special case: We reached the end.
         // There's 1 path, just containing the end
-        if name == "end" {
+        if name == route.to {
             return Ok(1);
         }
 
         let node = self
             .nodes
             .get(name)
-            .ok_or_else(|| format!("Can't find-a da node-a {}", name))?;
+            .ok_or_else(|| GraphError::UnknownCave(name.to_string()))?;
 
-        // If this is a small node, we might not be able to revisit it.
-        if !node.is_big && visited.contains(name) {
-            if name == "start" {
-                // Can't revisit start.
-                return Ok(0)
-            }
-            else if can_revisit_small {
-                can_revisit_small = false;
-            }
-            else {
-                // So this path is invalid. Return 0, meaning no path through here.
-                return Ok(0);
-            }
+        // We might not be able to visit this node again.
+        let count = visits.get(name).copied().unwrap_or(0);
+        if name == route.from && count > 0 {
+            return Ok(0);
+        }
+        if let Some(i) = self.loops_back(search.path.iter().map(String::as_str), name, policy) {
+            search.looped.insert(i, search.path.last().unwrap().clone());
+            return Ok(0);
         }
+        match policy.check_visit(name, node.is_big, count, extras_used) {
+            Visit::Allowed => {}
+            Visit::UsesExtra => extras_used += 1,
+            // So this path is invalid. Return 0, meaning no path through here.
+            Visit::NotAllowed => return Ok(0),
+        }
+
+        // Count this visit. Note that we need to copy the visits to keep each search state separate.
+        let mut new_visits = visits.clone();
+        new_visits.insert(name.to_string(), count + 1);
 
-        // Mark this node as visited. Note that we need to copy the visited set to keep each search state separate.
-        let mut new_visited = visited.clone();
-        new_visited.insert(name.to_string());
+        search.path.push(name.to_string());
+        let mut total = 0;
+        for n in node.neighbors.iter().filter(|n| !policy.is_forbidden(name, n)) {
+            total += self.number_of_paths_to_end(n, route, &new_visits, extras_used, policy, search)?;
+        }
+        search.path.pop();
 
-        node.neighbors
-            .iter()
-            .map(|n| self.number_of_paths_to_end(n, &new_visited, can_revisit_small))
-            .try_fold(0, |acc, r| r.map(move |v| acc + v))
+        // A loop came back here, and there's a way on to the end, so the path can go round it forever first.
+        if let Some(back_from) = search.looped.remove(&search.path.len()) {
+            if total > 0 && search.endless.is_none() {
+                search.endless = Some((name.to_string(), back_from));
+            }
+        }
+
+        Ok(total)
     }
 }
+
+/// What the brute force search has found out about loops so far.
+#[derive(Default)]
+struct BruteForce {
+    /// The caves on the path so far.
+    path: Vec<String>,
+    /// Places in the path a loop has come back to, and the cave it came back from.
+    looped: HashMap<usize, String>,
+    /// A loop that can get to the end.
+    endless: Option<(String, String)>,
+}
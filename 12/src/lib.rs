@@ -1,27 +1,60 @@
 use std::fs;
 
+mod counting;
+mod dot;
+mod error;
+mod export;
+mod generate;
 mod graph;
+mod paths;
+mod policy;
+mod shortest;
 
-use graph::Graph;
+pub use counting::{CaveIds, PathCount, PathCounter, VisitLimits};
+pub use error::GraphError;
+pub use generate::generate_caves;
+pub use graph::{is_uppercase, split_edge, Graph, Node, Route};
+pub use paths::{PathFilter, Paths};
+pub use policy::{RevisitPolicy, Visit};
+pub use shortest::WeightedPath;
 
-pub fn solve_pt1(filename: &str) -> u64 {
-    let contents = fs::read_to_string(filename).expect("Something totes went wrong with that file");
+pub fn solve_pt1(filename: &str) -> PathCount {
+    load_graph(filename).number_of_paths().expect("Cannae get the paths for yeh")
+}
+
+pub fn solve_pt2(filename: &str) -> PathCount {
+    load_graph(filename).number_of_paths_with_revisiting().expect("Cannae get the paths for yeh")
+}
 
-    let graph = Graph::from_str(&contents);
+/// Reads the caves from a file. Files ending in `.dot` are read as dot files, and anything else as puzzle input.
+pub fn load_graph(filename: &str) -> Graph {
+    let contents = fs::read_to_string(filename).expect("Something totes went wrong with that file");
 
-    // // Debug: Write to dot file to make sure the graph is correct.
-    // let dot_path = Path::new(filename).with_extension("dot");
-    // fs::write(dot_path, graph.to_dot()).expect("Failed to write dot file");
+    let graph = if filename.ends_with(".dot") {
+        Graph::from_dot(&contents)
+    } else {
+        contents.parse()
+    };
+    graph.unwrap_or_else(|e| panic!("Cannae parse the caves in {}: {}", filename, e))
+}
 
-    graph.number_of_paths().expect("Cannae get the paths for yeh")
+/// Counts the paths through the caves in the file that follow the policy.
+pub fn count_paths(filename: &str, route: Route, policy: &RevisitPolicy) -> PathCount {
+    load_graph(filename).count_paths(route, policy).expect("Cannae get the paths for yeh")
 }
 
-pub fn solve_pt2(filename: &str) -> u64 {
-    let contents = fs::read_to_string(filename).expect("Something totes went wrong with that file");
+/// Lists the paths through the caves in the file, like `start,A,b,end`.
+pub fn list_paths(filename: &str, route: Route, policy: &RevisitPolicy, filter: PathFilter) -> Vec<String> {
+    let graph = load_graph(filename);
 
-    let graph = Graph::from_str(&contents);
+    // Without a maximum length, the paths would never finish coming.
+    if filter.max_length.is_none() {
+        if let Ok(PathCount::Infinite { between: (a, b) }) = graph.count_paths(route, policy) {
+            panic!("Paths can go back and forth between {} and {} forever, so give them a maximum length", a, b);
+        }
+    }
 
-    graph.number_of_paths_with_revisiting().expect("Cannae get the paths for yeh")
+    graph.paths(route, policy, filter).map(|path| path.join(",")).collect()
 }
 
 #[cfg(test)]
@@ -30,31 +63,308 @@ mod tests {
 
     #[test]
     fn test_pt1_demo1() {
-        assert_eq!(solve_pt1("demo1.txt"), 10);
+        assert_eq!(solve_pt1("demo1.txt"), PathCount::Finite(10));
     }
 
     #[test]
     fn test_pt1_demo2() {
-        assert_eq!(solve_pt1("demo2.txt"), 19);
+        assert_eq!(solve_pt1("demo2.txt"), PathCount::Finite(19));
     }
 
     #[test]
     fn test_pt1_demo3() {
-        assert_eq!(solve_pt1("demo3.txt"), 226);
+        assert_eq!(solve_pt1("demo3.txt"), PathCount::Finite(226));
     }
 
     #[test]
     fn test_pt2_demo1() {
-        assert_eq!(solve_pt2("demo1.txt"), 36);
+        assert_eq!(solve_pt2("demo1.txt"), PathCount::Finite(36));
     }
 
     #[test]
     fn test_pt2_demo2() {
-        assert_eq!(solve_pt2("demo2.txt"), 103);
+        assert_eq!(solve_pt2("demo2.txt"), PathCount::Finite(103));
     }
 
     #[test]
     fn test_pt2_demo3() {
-        assert_eq!(solve_pt2("demo3.txt"), 3509);
+        assert_eq!(solve_pt2("demo3.txt"), PathCount::Finite(3509));
+    }
+
+    #[test]
+    fn test_memoised_counts() {
+        let mut inputs: Vec<String> = ["demo1.txt", "demo2.txt", "demo3.txt"]
+            .iter()
+            .map(|f| fs::read_to_string(f).unwrap())
+            .collect();
+        inputs.extend((0..5).map(|seed| generate_caves(12, seed)));
+
+        for input in &inputs {
+            let graph: Graph = input.parse().unwrap();
+            for policy in [RevisitPolicy::part1(), RevisitPolicy::part2()] {
+                let route = Route::default();
+                assert_eq!(graph.count_paths(route, &policy), graph.number_of_paths_brute_force(route, &policy), "{}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        let policies = [
+            RevisitPolicy::small_caves_twice(2),
+            RevisitPolicy::small_caves_twice(3),
+            RevisitPolicy::small_caves_up_to(3),
+            RevisitPolicy::part2().with_node_limit("A", 2),
+            RevisitPolicy::part2().with_node_limit("b", 1),
+            RevisitPolicy::part1().forbid_edge("A", "end"),
+            RevisitPolicy::part2().forbid_edge("c", "A").with_node_limit("start", 0),
+        ];
+
+        for filename in ["demo1.txt", "demo2.txt"] {
+            let graph: Graph = fs::read_to_string(filename).unwrap().parse().unwrap();
+            for policy in &policies {
+                let route = Route::default();
+                let count = graph.count_paths(route, policy);
+                assert_eq!(count, graph.number_of_paths_brute_force(route, policy), "{:?}", policy);
+                let listed = graph.paths(route, policy, PathFilter::default()).count() as u64;
+                assert_eq!(count, Ok(PathCount::Finite(listed)), "{:?}", policy);
+            }
+        }
+
+        // Every path through demo 1 has to go through A or b next to the end, so forbidding both leaves nothing.
+        let policy = RevisitPolicy::part2().forbid_edge("A", "end").forbid_edge("end", "b");
+        assert_eq!(count_paths("demo1.txt", Route::default(), &policy), PathCount::Finite(0));
+        // With no small caves allowed to be visited twice, it's the same as part 1.
+        assert_eq!(count_paths("demo1.txt", Route::default(), &RevisitPolicy::small_caves_twice(0)), PathCount::Finite(10));
+        // Visiting A at most twice rules out the two paths that go back to it via both b and c.
+        assert_eq!(count_paths("demo1.txt", Route::default(), &RevisitPolicy::part1().with_node_limit("A", 2)), PathCount::Finite(8));
+    }
+
+    #[test]
+    fn test_routes_and_sizes() {
+        let input = fs::read_to_string("demo1.txt").unwrap();
+        let graph: Graph = input.parse().unwrap();
+        let policy = RevisitPolicy::part1();
+
+        // Starting at d, the only way out is through b, which then can't be visited again.
+        let route = Route::new("d", "c");
+        assert_eq!(graph.count_paths(route, &policy), Ok(PathCount::Finite(9)));
+        assert_eq!(graph.number_of_paths_brute_force(route, &policy), Ok(PathCount::Finite(9)));
+        let paths: Vec<String> = graph.paths(route, &policy, PathFilter::default()).map(|p| p.join(",")).collect();
+        assert_eq!(paths.len(), 9);
+        assert!(paths.contains(&"d,b,end,A,start,A,c".to_string()));
+
+        assert!(graph.count_paths(Route::new("nowhere", "end"), &policy).is_err());
+        assert_eq!(graph.count_paths(Route::new("start", "nowhere"), &policy), Ok(PathCount::Finite(0)));
+
+        // With A small, there's only the paths that don't go through anything twice.
+        let all_small = Graph::parse_with(&input, |_| false).unwrap();
+        assert_eq!(all_small.number_of_paths(), Ok(PathCount::Finite(4)));
+        let annotated: Graph = format!("small: A\n{}", input).parse().unwrap();
+        assert_eq!(annotated.number_of_paths(), Ok(PathCount::Finite(4)));
+        let annotated = Graph::parse_with(&format!("{}big: A", input), |_| false).unwrap();
+        assert_eq!(annotated.number_of_paths(), Ok(PathCount::Finite(10)));
+    }
+
+    #[test]
+    fn test_weighted_paths() {
+        let graph: Graph = fs::read_to_string("weighted.txt").unwrap().parse().unwrap();
+        let route = Route::default();
+        let path = |caves: &[&str], cost| WeightedPath { caves: caves.iter().map(|c| c.to_string()).collect(), cost };
+
+        assert_eq!(graph.shortest_path(route), Ok(Some(path(&["start", "A", "end"], 6))));
+        assert_eq!(graph.shortest_path(Route::new("d", "c")), Ok(Some(path(&["d", "b", "A", "c"], 5))));
+        assert_eq!(graph.shortest_path(Route::new("start", "nowhere")), Ok(None));
+
+        assert_eq!(
+            graph.k_shortest_paths(route, 10),
+            Ok(vec![
+                path(&["start", "A", "end"], 6),
+                path(&["start", "b", "end"], 8),
+                path(&["start", "b", "A", "end"], 8),
+                path(&["start", "A", "b", "end"], 12),
+            ])
+        );
+        assert_eq!(graph.k_shortest_paths(route, 1).unwrap().len(), 1);
+
+        let policy = RevisitPolicy::part1().forbid_edge("A", "end");
+        assert_eq!(graph.cheapest_path(route, &policy), Ok(Some(path(&["start", "b", "end"], 8))));
+        assert_eq!(graph.cheapest_path(route, &policy.with_node_limit("b", 0)), Ok(None));
+
+        // Going from c means coming back through A, which is fine because it's big.
+        let policy = RevisitPolicy::part1().forbid_edge("A", "end").forbid_edge("A", "b");
+        let from_c = graph.cheapest_path(Route::new("c", "end"), &policy);
+        assert_eq!(from_c, Ok(Some(path(&["c", "A", "start", "b", "end"], 11))));
+
+        // Without weights, every edge costs 1.
+        let graph: Graph = fs::read_to_string("demo2.txt").unwrap().parse().unwrap();
+        assert_eq!(graph.shortest_path(route).unwrap().unwrap().cost, 2);
+    }
+
+    #[test]
+    fn test_list_paths() {
+        for (filename, pt1, pt2) in [("demo1.txt", 10, 36), ("demo2.txt", 19, 103), ("demo3.txt", 226, 3509)] {
+            assert_eq!(list_paths(filename, Route::default(), &RevisitPolicy::part1(), PathFilter::default()).len(), pt1);
+            assert_eq!(list_paths(filename, Route::default(), &RevisitPolicy::part2(), PathFilter::default()).len(), pt2);
+        }
+
+        let mut paths = list_paths("demo1.txt", Route::default(), &RevisitPolicy::part1(), PathFilter::default());
+        paths.sort();
+        assert_eq!(paths[0], "start,A,b,A,c,A,end");
+        assert!(paths.contains(&"start,b,end".to_string()));
+
+        let filter = PathFilter { through: vec!["c".to_string()], max_length: Some(6) };
+        let mut paths = list_paths("demo1.txt", Route::default(), &RevisitPolicy::part1(), filter);
+        paths.sort();
+        assert_eq!(paths, vec!["start,A,c,A,b,end", "start,A,c,A,end", "start,b,A,c,A,end"]);
+    }
+
+    #[test]
+    fn test_dot_import() {
+        for (filename, pt1, pt2) in [("demo1", 10, 36), ("demo2", 19, 103), ("demo3", 226, 3509)] {
+            let graph = load_graph(&format!("{}.dot", filename));
+            assert_eq!(graph.number_of_paths(), Ok(PathCount::Finite(pt1)), "{}", filename);
+            assert_eq!(graph.number_of_paths_with_revisiting(), Ok(PathCount::Finite(pt2)), "{}", filename);
+
+            // The dot files have every edge both ways round, but being strict they only count once.
+            let from_txt = load_graph(&format!("{}.txt", filename));
+            assert_eq!(graph.edges(), from_txt.edges(), "{}", filename);
+        }
+
+        let input = r#"
+            /* Comments, chains, subgraphs and attributes all get read. */
+            graph caves {
+                rankdir = LR
+                edge [weight=3]
+                start -- A -- "end" [color=blue]
+                subgraph cluster_0 { A -- b [weight=2]; b -- end }
+                # A node on its own still counts.
+                lonely [shape=box]
+            }
+        "#;
+        let graph = Graph::from_dot(input).unwrap();
+        assert_eq!(
+            graph.edges(),
+            vec![("A", "b", 2), ("A", "end", 3), ("A", "start", 3), ("b", "end", 3)]
+        );
+        assert!(graph.nodes.contains_key("lonely"));
+        assert_eq!(graph.number_of_paths(), Ok(PathCount::Finite(3)));
+
+        // Without strict, edges given twice are two different edges.
+        let graph = Graph::from_dot("graph { start -- end; end -- start }").unwrap();
+        assert_eq!(graph.number_of_paths(), Ok(PathCount::Finite(2)));
+
+        let error = GraphError::Dot { line: 3, message: "expected a name, found `}`".to_string() };
+        assert_eq!(Graph::from_dot("graph {\n  a -- \n}").err(), Some(error));
+        assert!(Graph::from_dot("graph { a -- b [weight=lots] }").is_err());
+        assert!(Graph::from_dot("caves { a -- b }").is_err());
+    }
+
+    #[test]
+    fn test_exports() {
+        let graph = load_graph("weighted.txt");
+
+        // Exporting to dot and reading it back gives the same graph.
+        let dot = graph.to_dot(&[]);
+        assert_eq!(Graph::from_dot(&dot).unwrap().edges(), graph.edges());
+        assert_eq!(dot.matches(" -- ").count(), graph.edges().len());
+        assert!(dot.contains("\"A\" [shape=box, style=bold];"));
+
+        let dot = graph.to_dot(&["start", "A", "end"]);
+        assert!(dot.contains("\"A\" [shape=box, style=bold, color=red, fontcolor=red];"));
+        assert!(dot.contains("\"b\";"));
+        assert_eq!(dot.matches("penwidth").count(), 2);
+
+        let graphml = graph.to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), graph.nodes.len());
+        assert_eq!(graphml.matches("<edge ").count(), graph.edges().len());
+        assert!(graphml.contains("<node id=\"A\"><data key=\"big\">true</data></node>"));
+
+        let json = load_graph("demo1.txt").to_json();
+        assert!(json.contains("\"d\": {\"big\": false, \"neighbors\": [{\"cave\": \"b\", \"cost\": 1}]}"));
+        assert_eq!(json.lines().count(), 8);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // Blank lines and stray whitespace are fine, and names can have dashes if the edge has spaces round it.
+        let graph: Graph = "\nstart-A\n  A - end  \n\nstart - north-west:3\nnorth-west - end\n".parse().unwrap();
+        assert_eq!(graph.number_of_paths(), Ok(PathCount::Finite(2)));
+        assert_eq!(graph.nodes["north-west"].costs, vec![3, 1]);
+        assert_eq!(split_edge("start - north-west"), Some(("start", "north-west")));
+        assert_eq!(split_edge("a-b-c"), None);
+
+        let parse = |input: &str| input.parse::<Graph>().err();
+        let bad_edge = |line, text: &str| Some(GraphError::BadEdge { line, text: text.to_string() });
+        assert_eq!(parse("start-A\nA"), bad_edge(2, "A"));
+        assert_eq!(parse("start-north-west"), bad_edge(1, "start-north-west"));
+        assert_eq!(parse("start-"), bad_edge(1, "start-"));
+        assert_eq!(parse("st art-A"), bad_edge(1, "st art-A"));
+        assert_eq!(parse("start-A:x"), Some(GraphError::BadCost { line: 1, cost: "x".to_string() }));
+        assert_eq!(parse("start-A\n\nb-b"), Some(GraphError::SelfLoop { line: 3, cave: "b".to_string() }));
+        assert_eq!(
+            parse("start-A\nA-end\nA-start:2"),
+            Some(GraphError::DuplicateEdge { line: 3, a: "A".to_string(), b: "start".to_string() })
+        );
+        assert_eq!(
+            parse("start-A\nA-end\nA-start:2").unwrap().to_string(),
+            "line 3: A and start are already connected"
+        );
+
+        let graph: Graph = "start-A\nb-end".parse().unwrap();
+        let no_route = GraphError::NoRoute { from: "start".to_string(), to: "end".to_string() };
+        assert_eq!(graph.check_route(Route::default()), Err(no_route));
+        assert_eq!(graph.number_of_paths(), Ok(PathCount::Finite(0)));
+        assert_eq!(load_graph("demo1.txt").check_route(Route::default()), Ok(()));
+        assert_eq!(graph.check_route(Route::new("b", "nowhere")), Err(GraphError::UnknownCave("nowhere".to_string())));
+        assert_eq!(GraphError::UnknownCave("nowhere".to_string()).to_string(), "there's no cave called `nowhere`");
+        assert_eq!(graph.count_paths(Route::new("nowhere", "end"), &RevisitPolicy::part1()), Err(GraphError::UnknownCave("nowhere".to_string())));
+    }
+
+    #[test]
+    fn test_infinite_paths() {
+        // A and B are both big and next to each other, so a path can go back and forth between them forever.
+        let graph = load_graph("infinite.txt");
+        let infinite = Ok(PathCount::Infinite { between: ("A".to_string(), "B".to_string()) });
+        for policy in [RevisitPolicy::part1(), RevisitPolicy::part2()] {
+            assert_eq!(graph.count_paths(Route::default(), &policy), infinite);
+            assert_eq!(graph.number_of_paths_brute_force(Route::default(), &policy), infinite);
+        }
+        assert_eq!(solve_pt1("infinite.txt").to_string(), "infinitely many, going back and forth between A and B");
+
+        // X and Y are next to each other too, but they can't be got to from the start.
+        let route = Route::default();
+        let finite = |policy: &RevisitPolicy| graph.count_paths(route, policy).unwrap();
+        assert_eq!(finite(&RevisitPolicy::part1().forbid_edge("B", "A")), PathCount::Finite(1));
+        let policy = RevisitPolicy::part1().with_node_limit("B", 2);
+        assert_eq!(finite(&policy), PathCount::Finite(8));
+        assert_eq!(graph.number_of_paths_brute_force(route, &policy), Ok(PathCount::Finite(8)));
+        assert_eq!(finite(&RevisitPolicy::part1().with_node_limit("B", 0)), PathCount::Finite(1));
+        // Paths can't go back to the start, so starting at A means B can only be visited once.
+        assert_eq!(graph.count_paths(Route::new("A", "end"), &RevisitPolicy::part1()), Ok(PathCount::Finite(2)));
+
+        // The paths can still be listed if there's a maximum length, and there's still a cheapest one.
+        let filter = PathFilter { through: Vec::new(), max_length: Some(6) };
+        let paths = list_paths("infinite.txt", route, &RevisitPolicy::part1(), filter);
+        assert_eq!(paths.len(), 5);
+        assert!(paths.contains(&"start,A,B,A,B,end".to_string()));
+        assert_eq!(graph.cheapest_path(route, &RevisitPolicy::part1()).unwrap().unwrap().cost, 3);
+    }
+
+    #[test]
+    fn test_loops_without_a_way_out() {
+        let route = Route::default();
+        let policy = RevisitPolicy::part1();
+        let check = |input: &str, path: &[&str]| {
+            let graph = input.parse::<Graph>().unwrap();
+            assert_eq!(graph.count_paths(route, &policy), Ok(PathCount::Finite(1)));
+            assert_eq!(graph.number_of_paths_brute_force(route, &policy), Ok(PathCount::Finite(1)));
+            assert_eq!(graph.paths(route, &policy, PathFilter::default()).collect::<Vec<_>>(), [path]);
+        };
+
+        // A and B go back and forth forever, but there's no way from them to the end.
+        check("start-A\nA-B\nstart-end", &["start", "end"]);
+        // The only way out of A and B is back through b, which has already been visited on the way in.
+        check("start-b\nb-A\nA-B\nb-end", &["start", "b", "end"]);
     }
 }
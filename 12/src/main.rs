@@ -1,13 +1,115 @@
 use advent2021_12::*;
 use std::env;
 
+fn usage(program: &str) -> ! {
+    panic!(
+        "Usage: {} [--paths [--through <cave>]... [--max-length <caves>] | --count | --shortest | --k-shortest <k> | --cheapest] \
+         [--export dot|graphml|json [--highlight <cave>,<cave>...]] [--from <cave>] [--to <cave>] \
+         [--revisit | --twice <caves> | --max-visits <visits>] [--limit <cave>=<visits>]... [--forbid <cave>-<cave>]... \
+         <filename>",
+        program
+    );
+}
+
+fn print_weighted_paths(paths: &[WeightedPath]) {
+    if paths.is_empty() {
+        println!("No paths");
+    }
+    for path in paths {
+        println!("{} (cost {})", path.caves.join(","), path.cost);
+    }
+}
+
 fn main() {
     // Get the path to the file to read
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Usage: {} <filename>", args[0]);
+
+    let mut filename = None;
+    let mut paths_mode = false;
+    let mut count_mode = false;
+    let mut shortest = None;
+    let mut cheapest_mode = false;
+    let mut policy = RevisitPolicy::part1();
+    let mut node_limits = Vec::new();
+    let mut forbidden_edges = Vec::new();
+    let mut filter = PathFilter::default();
+    let mut route = Route::default();
+    let mut export = None;
+    let mut highlight = Vec::new();
+
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().unwrap_or_else(|| usage(&args[0]));
+        let mut number = || value().parse().unwrap_or_else(|_| usage(&args[0]));
+
+        match arg.as_str() {
+            "--paths" => paths_mode = true,
+            "--count" => count_mode = true,
+            "--shortest" => shortest = Some(1),
+            "--k-shortest" => shortest = Some(number()),
+            "--cheapest" => cheapest_mode = true,
+            "--export" => export = Some(value()),
+            "--highlight" => highlight = value().split(',').collect(),
+            "--from" => route.from = value(),
+            "--to" => route.to = value(),
+            "--through" => filter.through.push(value().clone()),
+            "--max-length" => filter.max_length = Some(number()),
+            "--revisit" => policy = RevisitPolicy::part2(),
+            "--twice" => policy = RevisitPolicy::small_caves_twice(number()),
+            "--max-visits" => policy = RevisitPolicy::small_caves_up_to(number()),
+            "--limit" => {
+                let (cave, limit) = value().split_once('=').unwrap_or_else(|| usage(&args[0]));
+                node_limits.push((cave, limit.parse().unwrap_or_else(|_| usage(&args[0]))));
+            }
+            "--forbid" => forbidden_edges.push(split_edge(value()).unwrap_or_else(|| usage(&args[0]))),
+            _ if filename.is_none() => filename = Some(arg),
+            _ => usage(&args[0]),
+        }
+    }
+    let filename = filename.unwrap_or_else(|| usage(&args[0]));
+
+    for (cave, limit) in node_limits {
+        policy = policy.with_node_limit(cave, limit);
+    }
+    for (a, b) in forbidden_edges {
+        policy = policy.forbid_edge(a, b);
+    }
+
+    if let Some(format) = export {
+        let graph = load_graph(filename);
+        match format.as_str() {
+            "dot" => print!("{}", graph.to_dot(&highlight)),
+            "graphml" => print!("{}", graph.to_graphml()),
+            "json" => print!("{}", graph.to_json()),
+            _ => usage(&args[0]),
+        }
+        return;
+    }
+
+    // If there's no way along the route at all, it's probably a mistake in the file or the arguments.
+    if let Err(e) = load_graph(filename).check_route(route) {
+        eprintln!("Warning: {}", e);
+    }
+
+    if paths_mode {
+        for path in list_paths(filename, route, &policy, filter) {
+            println!("{}", path);
+        }
+        return;
+    }
+    if let Some(k) = shortest {
+        print_weighted_paths(&load_graph(filename).k_shortest_paths(route, k).unwrap_or_else(|e| panic!("{}", e)));
+        return;
+    }
+    if cheapest_mode {
+        let path = load_graph(filename).cheapest_path(route, &policy).unwrap_or_else(|e| panic!("{}", e));
+        print_weighted_paths(path.as_slice());
+        return;
+    }
+    if count_mode {
+        println!("Paths: {}", count_paths(filename, route, &policy));
+        return;
     }
-    let filename = &args[1];
 
     let result = solve_pt1(filename);
     println!("Part 1: {result}");
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use crate::graph::{Graph, Route};
+use crate::policy::{RevisitPolicy, Visit};
+
+/// Restrictions on which paths to list.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    /// Caves that every path has to go through.
+    pub through: Vec<String>,
+    /// The most caves a path can have, including the start and end.
+    pub max_length: Option<usize>,
+}
+
+impl PathFilter {
+    fn accepts(&self, path: &[&str]) -> bool {
+        self.through.iter().all(|cave| path.contains(&cave.as_str()))
+    }
+}
+
+/// Lists every path along a route, one at a time, so they don't all need to be held in memory.
+///
+/// This is a depth first search like `Graph::number_of_paths_to_end`, but with an explicit stack so it can stop after
+/// each path and carry on where it left off.
+///
+/// Without a maximum length, paths don't go round loops of caves that can be visited any number of times, since they
+/// could do that forever. With one, they go round as many times as fit.
+pub struct Paths<'a> {
+    graph: &'a Graph,
+    route: Route<'a>,
+    policy: &'a RevisitPolicy,
+    /// The path so far, along with how many of each cave's neighbours have been tried, and whether going into the
+    /// cave used up one of the extra visits.
+    stack: Vec<(&'a str, usize, bool)>,
+    /// How many times each cave appears in the path so far.
+    visits: HashMap<&'a str, usize>,
+    extras_used: usize,
+    filter: PathFilter,
+}
+
+impl<'a> Paths<'a> {
+    pub fn new(graph: &'a Graph, route: Route<'a>, policy: &'a RevisitPolicy, filter: PathFilter) -> Self {
+        let mut paths = Self {
+            graph,
+            route,
+            policy,
+            stack: Vec::new(),
+            visits: HashMap::new(),
+            extras_used: 0,
+            filter,
+        };
+
+        if let Some((name, _)) = graph.nodes.get_key_value(route.from) {
+            paths.enter(name);
+        }
+
+        paths
+    }
+
+    /// Adds a cave to the end of the path, if the policy allows it. Returns whether it did.
+    fn enter(&mut self, name: &'a str) -> bool {
+        let is_big = self.graph.nodes[name].is_big;
+        let count = self.visits.entry(name).or_insert(0);
+
+        let uses_extra = match self.policy.check_visit(name, is_big, *count, self.extras_used) {
+            Visit::Allowed => false,
+            Visit::UsesExtra => true,
+            Visit::NotAllowed => return false,
+        };
+
+        *count += 1;
+        if uses_extra {
+            self.extras_used += 1;
+        }
+        self.stack.push((name, 0, uses_extra));
+        true
+    }
+
+    fn pop(&mut self) {
+        let (name, _, used_extra) = self.stack.pop().unwrap();
+        *self.visits.get_mut(name).unwrap() -= 1;
+        if used_extra {
+            self.extras_used -= 1;
+        }
+    }
+}
+
+impl<'a> Iterator for Paths<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let &mut (name, ref mut tried, _) = self.stack.last_mut()?;
+            let neighbors = &self.graph.nodes[name].neighbors;
+
+            let Some(next) = neighbors.get(*tried) else {
+                // Tried everything from here, so go back a step.
+                self.pop();
+                continue;
+            };
+            *tried += 1;
+
+            // Paths can't go back to the start.
+            if next == self.route.from
+                || self.policy.is_forbidden(name, next)
+                || self.filter.max_length.is_some_and(|max| self.stack.len() >= max)
+            {
+                continue;
+            }
+
+            if next == self.route.to {
+                let path: Vec<&str> = self.stack.iter().map(|&(name, _, _)| name).chain([self.route.to]).collect();
+                if self.filter.accepts(&path) {
+                    return Some(path);
+                }
+                continue;
+            }
+
+            if self.filter.max_length.is_none()
+                && self.graph.loops_back(self.stack.iter().map(|&(name, _, _)| name), next, self.policy).is_some()
+            {
+                continue;
+            }
+
+            if let Some((next, _)) = self.graph.nodes.get_key_value(next.as_str()) {
+                self.enter(next);
+            }
+        }
+    }
+}
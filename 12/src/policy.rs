@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+/// Whether a path can go into a cave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    Allowed,
+    /// Allowed, but only by using up one of the small caves that can be visited extra times.
+    UsesExtra,
+    NotAllowed,
+}
+
+/// Rules for how many times a path can go through each cave.
+///
+/// Small caves can be visited `small_limit` times each, except that up to `extra_caves` of them can be visited up to
+/// `extra_limit` times instead. Big caves can be visited any number of times. Caves in `node_limits` ignore all that and
+/// just have the limit they're given, whether they're big or small.
+///
+/// Whatever the policy, paths never go back to the cave they started from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevisitPolicy {
+    pub small_limit: usize,
+    pub extra_caves: usize,
+    pub extra_limit: usize,
+    pub node_limits: HashMap<String, usize>,
+    /// Edges that can't be used, in either direction.
+    pub forbidden_edges: Vec<(String, String)>,
+}
+
+impl RevisitPolicy {
+    /// Small caves can be visited `limit` times each.
+    pub fn small_caves_up_to(limit: usize) -> Self {
+        Self {
+            small_limit: limit,
+            extra_caves: 0,
+            extra_limit: limit,
+            node_limits: HashMap::new(),
+            forbidden_edges: Vec::new(),
+        }
+    }
+
+    /// Up to `caves` of the small caves can be visited twice, and the rest once.
+    pub fn small_caves_twice(caves: usize) -> Self {
+        Self {
+            extra_caves: caves,
+            extra_limit: 2,
+            ..Self::small_caves_up_to(1)
+        }
+    }
+
+    /// Each small cave can only be visited once.
+    pub fn part1() -> Self {
+        Self::small_caves_up_to(1)
+    }
+
+    /// A single small cave can be visited twice.
+    pub fn part2() -> Self {
+        Self::small_caves_twice(1)
+    }
+
+    pub fn with_node_limit(mut self, name: &str, limit: usize) -> Self {
+        self.node_limits.insert(name.to_string(), limit);
+        self
+    }
+
+    pub fn forbid_edge(mut self, a: &str, b: &str) -> Self {
+        self.forbidden_edges.push((a.to_string(), b.to_string()));
+        self
+    }
+
+    /// The most times a cave can normally be visited, or None if there's no limit.
+    pub fn limit(&self, name: &str, is_big: bool) -> Option<usize> {
+        match self.node_limits.get(name) {
+            Some(&limit) => Some(limit),
+            None if is_big => None,
+            None => Some(self.small_limit),
+        }
+    }
+
+    /// The most times a cave can ever be visited, including if it's one of the extra ones.
+    pub fn max_visits(&self, name: &str, is_big: bool) -> Option<usize> {
+        let limit = self.limit(name, is_big)?;
+        Some(if self.can_go_over(name, is_big) { limit.max(self.extra_limit) } else { limit })
+    }
+
+    fn can_go_over(&self, name: &str, is_big: bool) -> bool {
+        !is_big && self.extra_caves > 0 && !self.node_limits.contains_key(name)
+    }
+
+    /// Whether a path can go into a cave it's already been through `visits` times, having used up `extras_used` of
+    /// the extra visits.
+    pub fn check_visit(&self, name: &str, is_big: bool, visits: usize, extras_used: usize) -> Visit {
+        let Some(limit) = self.limit(name, is_big) else {
+            return Visit::Allowed;
+        };
+
+        if visits < limit {
+            Visit::Allowed
+        } else if !self.can_go_over(name, is_big) || visits >= self.extra_limit {
+            Visit::NotAllowed
+        } else if visits > limit {
+            // This cave has already gone over its limit, so it's already counted as one of the extra ones.
+            Visit::Allowed
+        } else if extras_used < self.extra_caves {
+            Visit::UsesExtra
+        } else {
+            Visit::NotAllowed
+        }
+    }
+
+    pub fn is_forbidden(&self, a: &str, b: &str) -> bool {
+        self.forbidden_edges.iter().any(|(x, y)| (x == a && y == b) || (x == b && y == a))
+    }
+}
+
+impl Default for RevisitPolicy {
+    fn default() -> Self {
+        Self::part1()
+    }
+}
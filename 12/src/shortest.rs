@@ -0,0 +1,200 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::counting::{CaveIds, VisitLimits};
+
+/// A path through the caves, and how much it costs to follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightedPath {
+    pub caves: Vec<String>,
+    pub cost: u64,
+}
+
+impl WeightedPath {
+    fn from_ids(caves: &CaveIds, ids: &[usize], cost: u64) -> Self {
+        Self {
+            caves: ids.iter().map(|&id| caves.names[id].clone()).collect(),
+            cost,
+        }
+    }
+}
+
+/// Finds the cheapest path between two caves with Dijkstra's algorithm, ignoring whether caves are big or small.
+///
+/// Caves in `blocked_caves` can't be used at all, and nor can the edges in `blocked_edges`.
+fn dijkstra(
+    caves: &CaveIds,
+    start: usize,
+    end: usize,
+    blocked_caves: &[bool],
+    blocked_edges: &HashSet<(usize, usize)>,
+) -> Option<(Vec<usize>, u64)> {
+    let mut best = vec![u64::MAX; caves.names.len()];
+    let mut previous = vec![None; caves.names.len()];
+    let mut to_visit = BinaryHeap::new();
+
+    best[start] = 0;
+    to_visit.push(Reverse((0, start)));
+
+    while let Some(Reverse((cost, cave))) = to_visit.pop() {
+        if cost > best[cave] {
+            // Already found a cheaper way here.
+            continue;
+        }
+
+        if cave == end {
+            let mut path = vec![end];
+            while let Some(prev) = previous[*path.last().unwrap()] {
+                path.push(prev);
+            }
+            path.reverse();
+            return Some((path, cost));
+        }
+
+        for (&next, &edge_cost) in caves.neighbors[cave].iter().zip(&caves.costs[cave]) {
+            if blocked_caves[next] || blocked_edges.contains(&(cave, next)) {
+                continue;
+            }
+
+            let next_cost = cost + edge_cost;
+            if next_cost < best[next] {
+                best[next] = next_cost;
+                previous[next] = Some(cave);
+                to_visit.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+
+    None
+}
+
+/// The cost of following a path, taking the cheapest edge if there's more than one between two caves.
+fn path_cost(caves: &CaveIds, path: &[usize]) -> u64 {
+    path.windows(2)
+        .map(|pair| {
+            caves.neighbors[pair[0]]
+                .iter()
+                .zip(&caves.costs[pair[0]])
+                .filter(|&(&n, _)| n == pair[1])
+                .map(|(_, &cost)| cost)
+                .min()
+                .unwrap()
+        })
+        .sum()
+}
+
+/// Finds the cheapest path between two caves, ignoring whether caves are big or small.
+pub fn shortest_path(caves: &CaveIds, start: usize, end: usize) -> Option<WeightedPath> {
+    let (path, cost) = dijkstra(caves, start, end, &vec![false; caves.names.len()], &HashSet::new())?;
+    Some(WeightedPath::from_ids(caves, &path, cost))
+}
+
+/// Finds the `k` cheapest paths between two caves that don't go through any cave more than once, cheapest first, using
+/// Yen's algorithm.
+///
+/// Each path after the first is found by taking one of the paths already found, and branching off it somewhere with
+/// the rest of the path as cheap as possible. Paths that cost the same always come out in the same order for the same
+/// graph, but which one comes first depends on when they were found.
+pub fn k_shortest_paths(caves: &CaveIds, start: usize, end: usize, k: usize) -> Vec<WeightedPath> {
+    let num_caves = caves.names.len();
+    let mut found: Vec<(Vec<usize>, u64)> = Vec::new();
+    let mut candidates = BinaryHeap::new();
+    let mut seen = HashSet::new();
+
+    if let Some(first) = dijkstra(caves, start, end, &vec![false; num_caves], &HashSet::new()) {
+        seen.insert(first.0.clone());
+        candidates.push(Reverse((first.1, first.0)));
+    }
+
+    while found.len() < k {
+        let Some(Reverse((cost, path))) = candidates.pop() else {
+            break;
+        };
+        found.push((path.clone(), cost));
+
+        for i in 0..path.len() - 1 {
+            let root = &path[..=i];
+            let spur = path[i];
+
+            // Don't let the spur go the same way as any path we've already found with the same root.
+            let mut blocked_edges = HashSet::new();
+            for (other, _) in &found {
+                if other.len() > i + 1 && &other[..=i] == root {
+                    blocked_edges.insert((other[i], other[i + 1]));
+                    blocked_edges.insert((other[i + 1], other[i]));
+                }
+            }
+            // And don't let it go back through the root, so the path stays simple.
+            let mut blocked_caves = vec![false; num_caves];
+            for &cave in &root[..i] {
+                blocked_caves[cave] = true;
+            }
+
+            if let Some((spur_path, spur_cost)) = dijkstra(caves, spur, end, &blocked_caves, &blocked_edges) {
+                let mut candidate = root[..i].to_vec();
+                candidate.extend(spur_path);
+                if seen.insert(candidate.clone()) {
+                    candidates.push(Reverse((path_cost(caves, &root[..=i]) + spur_cost, candidate)));
+                }
+            }
+        }
+    }
+
+    found.iter().map(|(path, cost)| WeightedPath::from_ids(caves, path, *cost)).collect()
+}
+
+/// Finds the cheapest path that follows the visit limits, using Dijkstra's algorithm over both where we are and how
+/// many times each cave has been visited.
+pub fn cheapest_path(caves: &CaveIds, limits: &VisitLimits) -> Option<WeightedPath> {
+    let end = limits.end?;
+    let (visits, extras_used) = limits.visit(limits.start, 0, 0)?;
+
+    let start = (limits.start, visits);
+    let mut best = HashMap::from([(start, 0)]);
+    let mut previous: HashMap<(usize, u128), (usize, u128)> = HashMap::new();
+    let mut to_visit = BinaryHeap::from([Reverse((0, start, extras_used))]);
+
+    while let Some(Reverse((cost, state, extras_used))) = to_visit.pop() {
+        if cost > best[&state] {
+            continue;
+        }
+
+        let (cave, visits) = state;
+        if cave == end {
+            let mut path = vec![cave];
+            let mut state = state;
+            while let Some(&prev) = previous.get(&state) {
+                path.push(prev.0);
+                state = prev;
+            }
+            path.reverse();
+            return Some(WeightedPath::from_ids(caves, &path, cost));
+        }
+
+        for (&next, &edge_cost) in caves.neighbors[cave].iter().zip(&caves.costs[cave]) {
+            if !limits.can_move(cave, next) {
+                continue;
+            }
+
+            // Getting to the end finishes the path, so it doesn't count as a visit.
+            let (next_visits, next_extras) = if next == end {
+                (visits, extras_used)
+            } else {
+                match limits.visit(next, visits, extras_used) {
+                    Some(v) => v,
+                    None => continue,
+                }
+            };
+
+            let next_state = (next, next_visits);
+            let next_cost = cost + edge_cost;
+            if best.get(&next_state).is_none_or(|&b| next_cost < b) {
+                best.insert(next_state, next_cost);
+                previous.insert(next_state, state);
+                to_visit.push(Reverse((next_cost, next_state, next_extras)));
+            }
+        }
+    }
+
+    None
+}
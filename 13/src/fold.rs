@@ -4,14 +4,16 @@ use regex::Regex;
 
 use crate::point::Point;
 
-enum Axis {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
     X,
     Y,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Fold {
-    axis: Axis,
-    position: i32,
+    pub axis: Axis,
+    pub position: i32,
 }
 
 impl Fold {
@@ -28,27 +30,79 @@ impl Fold {
         Fold { axis, position }
     }
 
-    pub fn fold_point(&self, p: &Point) -> Point {
+    /// Folds a point on paper that's `width` by `height`. Points can't be on the fold line.
+    pub fn fold_point(&self, p: &Point, width: i32, height: i32) -> Result<Point, String> {
+        let (value, size) = match self.axis {
+            Axis::X => (p.x, width),
+            Axis::Y => (p.y, height),
+        };
+        if value == self.position {
+            return Err(format!("The dot at {},{} is right on the fold along {}", p.x, p.y, self));
+        }
+
+        let value = fold_value(value, self.position, size);
+        Ok(match self.axis {
+            Axis::X => Point { x: value, y: p.y },
+            Axis::Y => Point { x: p.x, y: value },
+        })
+    }
+
+    /// Every point on paper that's `width` by `height` that this fold would move to `p`.
+    pub fn unfold_point(&self, p: &Point, width: i32, height: i32) -> Vec<Point> {
+        match self.axis {
+            Axis::X => unfold_value(p.x, self.position, width).into_iter().map(|x| Point { x, y: p.y }).collect(),
+            Axis::Y => unfold_value(p.y, self.position, height).into_iter().map(|y| Point { x: p.x, y }).collect(),
+        }
+    }
+
+    /// The size of the paper after folding it, given how big it was before.
+    pub fn folded_size(&self, width: i32, height: i32) -> (i32, i32) {
+        match self.axis {
+            Axis::X => (folded_length(self.position, width), height),
+            Axis::Y => (width, folded_length(self.position, height)),
+        }
+    }
+}
+
+impl std::fmt::Display for Fold {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self.axis {
-            Axis::X => Point {
-                x: fold_value(p.x, self.position),
-                y: p.y,
-            },
-            Axis::Y => Point {
-                x: p.x,
-                y: fold_value(p.y, self.position),
-            },
+            Axis::X => write!(f, "x={}", self.position),
+            Axis::Y => write!(f, "y={}", self.position),
         }
     }
 }
 
-/// Fold a value along a fold line.
+/// How far everything has to move along after folding so nothing's negative. That's only needed if the bit being
+/// folded over is longer than the bit it's folded onto.
+fn offset(fold: i32, size: i32) -> i32 {
+    (size - 1 - 2 * fold).max(0)
+}
+
+/// How long paper that's `size` long is after folding it.
+fn folded_length(fold: i32, size: i32) -> i32 {
+    fold.max(size - 1 - fold)
+}
+
+/// Fold a value along a fold line, on paper that's `size` long.
 ///
 /// Values < fold are unchanged.
 /// Values > fold are folded to the other side of the fold line.
-/// Undefined behavior if value == fold.
-fn fold_value(value: i32, fold: i32) -> i32 {
-    min(value, 2 * fold - value)
+/// Then everything's moved along if it needs to be, so the smallest value is 0.
+/// Values can't be on the fold, and `fold_point` checks for that.
+fn fold_value(value: i32, fold: i32, size: i32) -> i32 {
+    min(value, 2 * fold - value) + offset(fold, size)
+}
+
+/// Every value on paper that's `size` long that `fold_value` would turn into `value`.
+fn unfold_value(value: i32, fold: i32, size: i32) -> Vec<i32> {
+    let value = value - offset(fold, size);
+    let mut values: Vec<i32> = [value, 2 * fold - value]
+        .into_iter()
+        .filter(|&v| v >= 0 && v < size && v != fold)
+        .collect();
+    values.dedup();
+    values
 }
 
 // Ascii diagram for me to figure things out:
@@ -61,3 +115,9 @@ fn fold_value(value: i32, fold: i32) -> i32 {
 // 6 -> 2 = 4 - (6 - 4)
 // 7 -> 1 = 4 - (7 - 4)
 // 8 -> 0 = 4 - (8 - 4)
+
+// And when the bottom bit's longer, everything moves along by 6 - 2 * 2 = 2:
+
+// 0 1 2 3 4 5 6
+// . . | . . . .
+// 2 3 x 3 2 1 0
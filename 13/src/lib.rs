@@ -2,20 +2,33 @@ use std::{fs, collections::HashSet};
 
 mod point;
 mod fold;
+mod ocr;
+mod paper;
+mod transform;
 
-use point::Point;
-use fold::Fold;
+pub use point::Point;
+pub use fold::{Axis, Fold};
+pub use ocr::{read_letters, Reading, UnknownGlyph};
+pub use paper::Paper;
+pub use transform::FoldTransform;
 
-pub fn solve_pt1(filename: &str) -> u64 {
+/// Reads the paper and the folds to make from a file.
+pub fn parse(filename: &str) -> (Paper, Vec<Fold>) {
     let contents = fs::read_to_string(filename).expect("Eyyy, something's wrong with ya input file ya dummy!");
 
     let mut lines = contents.lines();
     let dots: HashSet<_> = lines.by_ref().take_while(|l| !l.is_empty()).map(Point::from_string).collect();
+    let folds = lines.map(Fold::from_string).collect();
+
+    (Paper::new(dots), folds)
+}
+
+pub fn solve_pt1(filename: &str) -> u64 {
+    let (paper, folds) = parse(filename);
 
-    let first_fold = lines.next().map(Fold::from_string).unwrap();
-    let dots = dots.iter().map(|p| first_fold.fold_point(p)).collect::<HashSet<_>>();
+    let paper = paper.fold_all(&folds[..1]).unwrap_or_else(|e| panic!("Ya can't fold that, ya dummy! {}", e));
 
-    dots.len() as u64
+    paper.dots.len() as u64
 }
 
 /// Returns an ascii diagram of all the dots in the hashset.
@@ -38,16 +51,11 @@ fn dots_to_string(dots: &HashSet<Point>) -> String {
 }
 
 pub fn solve_pt2(filename: &str) -> String {
-    let contents = fs::read_to_string(filename).expect("Eyyy, something's wrong with ya input file ya dummy!");
-
-    let mut lines = contents.lines();
-    let mut dots: HashSet<_> = lines.by_ref().take_while(|l| !l.is_empty()).map(Point::from_string).collect();
+    let (paper, folds) = parse(filename);
 
-    for fold in lines.map(Fold::from_string) {
-        dots = dots.iter().map(|p| fold.fold_point(p)).collect::<HashSet<_>>();
-    }
+    let paper = paper.fold_all(&folds).unwrap_or_else(|e| panic!("Ya can't fold that, ya dummy! {}", e));
 
-    dots_to_string(&dots)
+    dots_to_string(&paper.dots)
 }
 
 #[cfg(test)]
@@ -58,4 +66,98 @@ mod tests {
     fn test_pt1() {
         assert_eq!(solve_pt1("demo.txt"), 17);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pt2() {
+        let reading = read_letters(&solve_pt2("letters.txt"));
+        assert_eq!(reading.text, "JOKERHUB");
+        assert!(reading.is_complete());
+
+        // The demo folds into a square, which isn't a letter.
+        let art = solve_pt2("demo.txt");
+        assert_eq!(art, "#####\n#...#\n#...#\n#...#\n#####\n");
+        let reading = read_letters(&art);
+        assert_eq!(reading.text, "?");
+        assert_eq!(reading.unknown, vec![UnknownGlyph { index: 0, column: 0, art }]);
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let art = ".##..###..####.\n#..#.#..#.#....\n#..#.###..###..\n####.#..#.#....\n#..#.#..#.#....\n#..#.###..####.\n";
+        assert_eq!(read_letters(art).to_string(), "ABE");
+
+        // A dot in the gap after the B means it can't be read.
+        let art = art.replacen("###..####", "###.#####", 1);
+        let reading = read_letters(&art);
+        assert_eq!(reading.text, "A?E");
+        assert_eq!(reading.unknown[0].index, 1);
+        assert_eq!(reading.unknown[0].column, 5);
+        assert!(reading.to_string().starts_with("A?E\nCouldn't read letter 2 at column 5:\n###.#\n#..#.\n"));
+    }
+
+    #[test]
+    fn test_folds() {
+        let dots = |points: &[(i32, i32)]| points.iter().map(|&(x, y)| Point { x, y }).collect::<HashSet<_>>();
+        let paper = Paper::new(dots(&[(0, 0), (1, 0), (3, 0), (6, 0), (6, 1)]));
+        assert_eq!((paper.width, paper.height), (7, 2));
+
+        // Folding in the middle is just like the puzzle.
+        let fold = Fold { axis: Axis::X, position: 3 };
+        assert!(paper.fold(&fold).unwrap_err().contains("3,0"));
+
+        // The bit after x=2 is longer, so it sticks out past 0 and everything moves along by 2.
+        let fold = Fold { axis: Axis::X, position: 2 };
+        let folded = paper.fold(&fold).unwrap();
+        assert_eq!((folded.width, folded.height), (4, 2));
+        assert_eq!(folded.dots, dots(&[(2, 0), (3, 0), (0, 0), (0, 1)]));
+        assert_eq!(paper.unfold(&[fold], &Point { x: 3, y: 0 }), vec![Point { x: 1, y: 0 }, Point { x: 3, y: 0 }]);
+        assert_eq!(paper.unfold(&[fold], &Point { x: 0, y: 1 }), vec![Point { x: 6, y: 1 }]);
+
+        // Every dot can be found again by unfolding where it ended up.
+        let (paper, folds) = parse("letters.txt");
+        let mut folded = paper.clone();
+        for fold in &folds {
+            folded = folded.fold(fold).unwrap();
+        }
+        let unfolded: HashSet<Point> = folded.dots.iter().flat_map(|p| paper.unfold(&folds, p)).collect();
+        assert!(paper.dots.is_subset(&unfolded));
+        // There's three folds, so each dot could have come from up to 8 places.
+        assert!(folded.dots.iter().all(|p| paper.unfold(&folds, p).len() == 8));
+    }
+
+    #[test]
+    fn test_fold_all() {
+        let fold_one_by_one = |paper: &Paper, folds: &[Fold]| {
+            folds.iter().try_fold(paper.clone(), |paper, fold| paper.fold(fold))
+        };
+
+        for filename in ["demo.txt", "letters.txt"] {
+            let (paper, folds) = parse(filename);
+            let folded = paper.fold_all(&folds).unwrap();
+            let expected = fold_one_by_one(&paper, &folds).unwrap();
+            assert_eq!((folded.dots, folded.width, folded.height), (expected.dots, expected.width, expected.height));
+        }
+
+        // Lots of dots, with folds that aren't in the middle, and along the same axis twice in a row.
+        let folds = [(Axis::Y, 150), (Axis::X, 400), (Axis::X, 170), (Axis::Y, 20), (Axis::Y, 300), (Axis::X, 3)]
+            .map(|(axis, position)| Fold { axis, position });
+        // Make the dots by unfolding some from the folded paper, so none of them land on a fold line.
+        let blank = Paper { dots: HashSet::new(), width: 613, height: 251 };
+        let dots = (0..2000)
+            .map(|i: i32| Point { x: i * 7919 % 170, y: i * 104729 % 130 })
+            .flat_map(|p| blank.unfold(&folds, &p))
+            .collect();
+        let paper = Paper { dots, ..blank };
+        assert!(paper.dots.len() > 10000);
+
+        let folded = paper.fold_all(&folds).unwrap();
+        let expected = fold_one_by_one(&paper, &folds).unwrap();
+        assert_eq!((folded.dots, folded.width, folded.height), (expected.dots, expected.width, expected.height));
+
+        // A dot that only lands on a fold line after being folded is still an error.
+        let paper = Paper::new([Point { x: 0, y: 0 }, Point { x: 5, y: 0 }].into_iter().collect());
+        let folds = [Fold { axis: Axis::X, position: 3 }, Fold { axis: Axis::X, position: 1 }];
+        assert_eq!(paper.fold_all(&folds).unwrap_err(), "The dot at 5,0 lands right on the fold along x=1");
+        assert!(fold_one_by_one(&paper, &folds).is_err());
+    }
+}
@@ -12,6 +12,7 @@ fn main() {
     let result = solve_pt1(filename);
     println!("Part 1: {result}");
 
-    let result = solve_pt2(filename);
-    println!("Part 2:\n{result}");
+    let art = solve_pt2(filename);
+    println!("Part 2:\n{art}");
+    println!("Which says: {}", read_letters(&art));
 }
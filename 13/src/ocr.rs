@@ -0,0 +1,103 @@
+use std::fmt;
+
+/// How wide and tall each letter is, not counting the blank column after it.
+const LETTER_WIDTH: usize = 4;
+const LETTER_HEIGHT: usize = 6;
+
+/// The letters the puzzles use, drawn the same way as `dots_to_string`.
+const FONT: [(char, &str); 17] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+/// A letter that isn't in the font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownGlyph {
+    /// Which letter it is, counting from 0.
+    pub index: usize,
+    /// The column it starts at.
+    pub column: usize,
+    pub art: String,
+}
+
+/// The text read out of some ascii art, with a `?` for each letter that couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reading {
+    pub text: String,
+    pub unknown: Vec<UnknownGlyph>,
+}
+
+impl Reading {
+    /// Whether every letter could be read.
+    pub fn is_complete(&self) -> bool {
+        self.unknown.is_empty()
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)?;
+        for glyph in &self.unknown {
+            write!(f, "\nCouldn't read letter {} at column {}:\n{}", glyph.index + 1, glyph.column, glyph.art)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the letters out of ascii art like `dots_to_string` makes.
+///
+/// Letters are 4 columns wide with a blank column between each one, and 6 rows tall, starting from the top left.
+/// Anything that doesn't match the font, including art that's too tall, comes out as a `?`.
+pub fn read_letters(art: &str) -> Reading {
+    let rows: Vec<&[u8]> = art.lines().map(str::as_bytes).collect();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let is_dot = |x: usize, y: usize| rows.get(y).and_then(|row| row.get(x)) == Some(&b'#');
+
+    let mut reading = Reading {
+        text: String::new(),
+        unknown: Vec::new(),
+    };
+
+    for (index, column) in (0..width).step_by(LETTER_WIDTH + 1).enumerate() {
+        let glyph: String = (0..LETTER_HEIGHT)
+            .flat_map(|y| (column..column + LETTER_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| if is_dot(x, y) { '#' } else { '.' })
+            .collect();
+
+        // Nothing else can be in the letter's box: not the gap after it, and not anything below it.
+        let spills_over = (0..rows.len()).any(|y| is_dot(column + LETTER_WIDTH, y))
+            || (LETTER_HEIGHT..rows.len()).any(|y| (column..column + LETTER_WIDTH).any(|x| is_dot(x, y)));
+
+        match FONT.iter().find(|&&(_, art)| art == glyph) {
+            Some(&(letter, _)) if !spills_over => reading.text.push(letter),
+            _ => {
+                reading.text.push('?');
+                let art = rows
+                    .iter()
+                    .map(|row| {
+                        let end = row.len().min(column + LETTER_WIDTH + 1);
+                        String::from_utf8_lossy(&row[column.min(end)..end]).into_owned() + "\n"
+                    })
+                    .collect();
+                reading.unknown.push(UnknownGlyph { index, column, art });
+            }
+        }
+    }
+
+    reading
+}
@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+
+use crate::fold::Fold;
+use crate::point::Point;
+use crate::transform::FoldTransform;
+
+/// A transparent sheet of paper with some dots on it.
+#[derive(Debug, Clone)]
+pub struct Paper {
+    pub dots: HashSet<Point>,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Paper {
+    /// Paper just big enough for all the dots.
+    pub fn new(dots: HashSet<Point>) -> Paper {
+        let width = dots.iter().map(|p| p.x + 1).max().unwrap_or(0);
+        let height = dots.iter().map(|p| p.y + 1).max().unwrap_or(0);
+        Paper { dots, width, height }
+    }
+
+    /// Folds the paper. It's an error for there to be a dot on the fold line.
+    ///
+    /// Folds don't have to be in the middle. If the bit that's folded over is longer, it ends up sticking out past the
+    /// edge, and the folded paper starts there instead. The paper might really be bigger than the dots on it, so a
+    /// fold past the last dot is fine, and just leaves them where they are.
+    pub fn fold(&self, fold: &Fold) -> Result<Paper, String> {
+        let (width, height) = fold.folded_size(self.width, self.height);
+        let dots = self
+            .dots
+            .iter()
+            .map(|p| fold.fold_point(p, self.width, self.height))
+            .collect::<Result<_, _>>()?;
+        Ok(Paper { dots, width, height })
+    }
+
+    /// Makes all the folds, one after the other. This gives the same paper as calling `fold` for each of them, but
+    /// works out where each dot ends up first, then moves all the dots in one go.
+    pub fn fold_all(&self, folds: &[Fold]) -> Result<Paper, String> {
+        let transform = FoldTransform::new(folds, self.width, self.height);
+
+        let mut dots = HashSet::with_capacity(self.dots.len());
+        for p in &self.dots {
+            dots.insert(transform.apply(p)?);
+        }
+        Ok(Paper { dots, width: transform.width, height: transform.height })
+    }
+
+    /// Every point on this paper that would end up at `p` after the folds.
+    pub fn unfold(&self, folds: &[Fold], p: &Point) -> Vec<Point> {
+        // The size of the paper before each fold.
+        let mut sizes = vec![(self.width, self.height)];
+        for fold in folds {
+            let &(width, height) = sizes.last().unwrap();
+            sizes.push(fold.folded_size(width, height));
+        }
+
+        let mut points = vec![*p];
+        for (fold, &(width, height)) in folds.iter().zip(&sizes).rev() {
+            points = points.iter().flat_map(|p| fold.unfold_point(p, width, height)).collect();
+        }
+        points
+    }
+}
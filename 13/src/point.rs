@@ -1,4 +1,5 @@
 #[derive(Debug)]
+#[derive(Clone, Copy)]
 #[derive(PartialEq)]
 #[derive(Eq)]
 #[derive(Hash)]
@@ -0,0 +1,57 @@
+use crate::fold::{Axis, Fold};
+use crate::point::Point;
+
+/// Where each value along one axis ends up after all the folds along that axis, or the fold whose line it lands on.
+fn axis_map(folds: &[&Fold], size: i32) -> Vec<Result<i32, Fold>> {
+    let mut values: Vec<Result<i32, Fold>> = (0..size).map(Ok).collect();
+    let mut size = size;
+
+    for &&fold in folds {
+        // Folding works the same along either axis, so pretend everything's along x.
+        let along_x = Fold { axis: Axis::X, position: fold.position };
+        for value in values.iter_mut() {
+            if let Ok(v) = *value {
+                *value = along_x.fold_point(&Point { x: v, y: 0 }, size, 1).map(|p| p.x).map_err(|_| fold);
+            }
+        }
+        size = along_x.folded_size(size, 1).0;
+    }
+
+    values
+}
+
+/// A list of folds squashed into one lookup table per axis, so each dot can be moved straight to where it ends up.
+///
+/// Folds along x only move dots along x, and the same for y, so each axis can be worked out on its own. That's one
+/// pass over each axis per fold, rather than one pass over every dot.
+pub struct FoldTransform {
+    x: Vec<Result<i32, Fold>>,
+    y: Vec<Result<i32, Fold>>,
+    /// The size of the paper after all the folds.
+    pub width: i32,
+    pub height: i32,
+}
+
+impl FoldTransform {
+    /// Works out the lookup tables for paper that's `width` by `height`.
+    pub fn new(folds: &[Fold], width: i32, height: i32) -> FoldTransform {
+        let along = |axis| folds.iter().filter(|fold| fold.axis == axis).collect::<Vec<_>>();
+
+        let x = axis_map(&along(Axis::X), width);
+        let y = axis_map(&along(Axis::Y), height);
+        let (width, height) = folds.iter().fold((width, height), |(w, h), fold| fold.folded_size(w, h));
+        FoldTransform { x, y, width, height }
+    }
+
+    /// Where a dot ends up after all the folds. It's an error if it's on one of the fold lines along the way, or if it's
+    /// not on the paper the transform was made for.
+    pub fn apply(&self, p: &Point) -> Result<Point, String> {
+        let (Some(&x), Some(&y)) = (self.x.get(p.x as usize), self.y.get(p.y as usize)) else {
+            return Err(format!("The dot at {},{} is off the edge of the paper", p.x, p.y));
+        };
+        let on_fold = |fold: Fold| format!("The dot at {},{} lands right on the fold along {}", p.x, p.y, fold);
+        let x = x.map_err(on_fold)?;
+        let y = y.map_err(on_fold)?;
+        Ok(Point { x, y })
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::{thread, time::Duration};
+
+use crate::grid::Grid;
+
+/// Settings for animating the basin search in the terminal.
+#[derive(Debug, Clone)]
+pub struct AnimationSettings {
+    /// How long to wait between frames.
+    pub frame_delay: Duration,
+    /// Start paused, so each frame has to be stepped through by pressing enter.
+    pub step: bool,
+}
+
+impl AnimationSettings {
+    pub fn from_fps(fps: f64, step: bool) -> Self {
+        let frame_delay = if fps > 0.0 {
+            Duration::from_secs_f64(1.0 / fps)
+        } else {
+            Duration::ZERO
+        };
+        Self { frame_delay, step }
+    }
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self::from_fps(20.0, false)
+    }
+}
+
+/// Commands typed on stdin while the animation is running.
+enum Command {
+    /// Just pressing enter. Pauses when playing, and steps one frame when paused.
+    Enter,
+    Play,
+    Quit,
+}
+
+impl Command {
+    fn from_line(line: &str) -> Self {
+        match line.trim() {
+            "p" | "play" => Command::Play,
+            "q" | "quit" => Command::Quit,
+            _ => Command::Enter,
+        }
+    }
+}
+
+/// Renders frames of the basin search to the terminal.
+///
+/// The buffers are kept between frames, so drawing a frame is linear in the size of the grid, rather than scanning
+/// the whole to_visit list for every cell.
+pub struct Animator {
+    settings: AnimationSettings,
+    frontier: Vec<Vec<bool>>,
+    buffer: String,
+    commands: Receiver<Command>,
+    paused: bool,
+    finished: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Style {
+    Plain,
+    Wall,
+    Visited,
+    Frontier,
+}
+
+impl Style {
+    fn escape_code(&self) -> &'static str {
+        match self {
+            Style::Plain => "\x1b[0m",
+            // Dark grey
+            Style::Wall => "\x1b[0;90m",
+            // Green
+            Style::Visited => "\x1b[1;32m",
+            // Yellow
+            Style::Frontier => "\x1b[1;33m",
+        }
+    }
+}
+
+impl Animator {
+    pub fn new(grid: &Grid, settings: AnimationSettings) -> Self {
+        // Read commands on a separate thread so that playback doesn't block waiting for input.
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(Command::from_line(&line)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Clear the screen once. Each frame after that just moves the cursor back to the top left.
+        print!("\x1b[2J");
+
+        Self {
+            paused: settings.step,
+            settings,
+            frontier: vec![vec![false; grid.w]; grid.h],
+            buffer: String::with_capacity(grid.w * grid.h * 2),
+            commands,
+            finished: false,
+        }
+    }
+
+    /// Prints one frame of the animation, then waits until it's time for the next one.
+    pub fn frame(&mut self, grid: &Grid, visited: &[Vec<bool>], to_visit: &[(i32, i32)]) {
+        if self.finished {
+            return;
+        }
+
+        self.render(grid, visited, to_visit);
+
+        let mut stdout = io::stdout().lock();
+        // Not much we can do if the terminal's gone away.
+        let _ = stdout.write_all(self.buffer.as_bytes());
+        let _ = stdout.flush();
+        drop(stdout);
+
+        self.wait();
+    }
+
+    fn wait(&mut self) {
+        if !self.paused {
+            thread::sleep(self.settings.frame_delay);
+            match self.commands.try_recv() {
+                Ok(Command::Enter) => self.paused = true,
+                Ok(Command::Quit) => self.finished = true,
+                Ok(Command::Play) | Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => {}
+            }
+            return;
+        }
+
+        match self.commands.recv() {
+            Ok(Command::Enter) => {}
+            Ok(Command::Play) => self.paused = false,
+            Ok(Command::Quit) => self.finished = true,
+            // No more input, so nothing will ever unpause us.
+            Err(_) => self.paused = false,
+        }
+    }
+
+    /// Draws the grid into the buffer, using terminal colors to represent whether a cell is visited, in the to_visit
+    /// list, or a wall.
+    fn render(&mut self, grid: &Grid, visited: &[Vec<bool>], to_visit: &[(i32, i32)]) {
+        self.frontier.iter_mut().for_each(|r| r.fill(false));
+        for &(x, y) in to_visit {
+            if grid.in_bounds(x, y) {
+                self.frontier[y as usize][x as usize] = true;
+            }
+        }
+
+        self.buffer.clear();
+        // Move the cursor to the top left.
+        self.buffer.push_str("\x1b[H");
+
+        for (y, (frontier_row, visited_row)) in self.frontier.iter().zip(visited).enumerate() {
+            let mut current = Style::Plain;
+            for (x, (&is_frontier, &is_visited)) in frontier_row.iter().zip(visited_row).enumerate() {
+                let z = grid.get(x as i32, y as i32);
+                // The to_visit list can contain cells that have already been visited or are too high, which will be
+                // skipped when they're popped, so those are drawn as what they are rather than as the frontier.
+                let style = if is_visited {
+                    Style::Visited
+                } else if z >= 9 {
+                    Style::Wall
+                } else if is_frontier {
+                    Style::Frontier
+                } else {
+                    Style::Plain
+                };
+
+                // Only change the color when we need to, which keeps the frames small.
+                if style != current {
+                    self.buffer.push_str(style.escape_code());
+                    current = style;
+                }
+                let _ = write!(self.buffer, "{}", z);
+            }
+            self.buffer.push_str(Style::Plain.escape_code());
+            self.buffer.push('\n');
+        }
+
+        let status = if self.paused {
+            "Paused: enter to step, p to play, q to skip to the end"
+        } else {
+            "Playing: enter to pause, q to skip to the end"
+        };
+        // Clear the rest of the line, in case the previous status was longer.
+        let _ = writeln!(self.buffer, "{}\x1b[K", status);
+    }
+}
@@ -1,7 +1,14 @@
+mod animation;
 mod grid;
+mod merge_tree;
+mod water;
 
-use grid::Grid;
-use std::{thread, time::Duration};
+pub use animation::AnimationSettings;
+pub use grid::Grid;
+pub use merge_tree::{Basin, MergeTree};
+pub use water::{flood_steps, FloodStep, Merge, WaterMap};
+
+use animation::Animator;
 
 const DELTAS: [(i32, i32); 4] = [
     (-1, 0),
@@ -27,7 +34,7 @@ pub fn solve_pt1(filename: &str) -> u32 {
         for y in -1..=(grid.h as i32) {
             let z = grid.get(x, y);
             let is_lowest = DELTAS.iter()
-                .map(|(dy, dx)| grid.get(x + dx, y + dy) > z).all(|b| b);
+                .all(|(dy, dx)| grid.get(x + dx, y + dy) > z);
             if is_lowest {
                 risk += z + 1
             }
@@ -37,7 +44,13 @@ pub fn solve_pt1(filename: &str) -> u32 {
     risk
 }
 
-fn find_basin_size(grid: &Grid, visited: &mut [Vec<bool>], x: i32, y: i32) -> i32 {
+fn find_basin_size(
+    grid: &Grid,
+    visited: &mut [Vec<bool>],
+    x: i32,
+    y: i32,
+    mut animator: Option<&mut Animator>,
+) -> i32 {
     let mut to_visit = vec![(x, y)];
 
     let mut basin_size = 0;
@@ -69,64 +82,33 @@ fn find_basin_size(grid: &Grid, visited: &mut [Vec<bool>], x: i32, y: i32) -> i3
 
         to_visit.extend(neighbors);
 
-        // Print the frame
-        // print_frame(grid, visited, &to_visit);
+        if let Some(animator) = animator.as_deref_mut() {
+            animator.frame(grid, visited, &to_visit);
+        }
     }
 
     basin_size
 }
 
-/// Prints one frame of an animation to the terminal.
-#[allow(unused)]
-fn print_frame(grid: &Grid, visited: &[Vec<bool>], to_visit: &[(i32, i32)]) {
-    // Clear the screen
-    print!("\x1b[2J");
-
-    // Print the grid
-    print_grid(grid, visited, to_visit);
-
-    // Sleep for a bit
-    thread::sleep(Duration::from_millis(150));
-}
-
-/// Prints the grid, using terminal colors to represent whether a cell is visited, or in the to_visit list.
-fn print_grid(grid: &Grid, visited: &[Vec<bool>], to_visit: &[(i32, i32)]) {
-    for y in 0..=(grid.h as i32 - 1) {
-        for x in 0..=(grid.w as i32 - 1) {
-            let z = grid.get(x, y);
-            let is_visited = visited[y as usize][x as usize];
-            let is_to_visit = to_visit.iter().any(|(tx, ty)| tx == &x && ty == &y);
-
-            if is_to_visit {
-                // Print in yellow
-                print!("\x1b[1;33m{}\x1b[0m", z);
-            } else if is_visited {
-                // Print in green
-                print!("\x1b[1;32m{}\x1b[0m", z);
-            } else {
-                print!("{}", z);
-            }
-        }
-        println!();
-    }
-}
-
-pub fn solve_pt2(filename: &str) -> i32 {
-    let grid = parse_input(filename);
-
+fn find_basin_sizes(grid: &Grid, mut animator: Option<&mut Animator>) -> Vec<i32> {
     let mut basin_sizes: Vec<i32> = vec![];
     let mut visited = vec![vec![false; grid.w]; grid.h];
 
     for x in 0..=(grid.w as i32 - 1) {
         for y in 0..=(grid.h as i32 - 1) {
             // Do a search to find the basin size
-            let basin_size = find_basin_size(&grid, &mut visited, x, y);
+            let basin_size = find_basin_size(grid, &mut visited, x, y, animator.as_deref_mut());
             if basin_size > 0 {
                 basin_sizes.push(basin_size);
             }
         }
     }
 
+    basin_sizes
+}
+
+/// Multiplies together the sizes of the 3 largest basins.
+fn largest_basins_product(mut basin_sizes: Vec<i32>) -> i32 {
     // println!("{:?}", basin_sizes);
 
     basin_sizes.sort();
@@ -136,6 +118,35 @@ pub fn solve_pt2(filename: &str) -> i32 {
     largest_basin_sizes.product()
 }
 
+pub fn solve_pt2(filename: &str) -> i32 {
+    let grid = parse_input(filename);
+
+    largest_basins_product(find_basin_sizes(&grid, None))
+}
+
+/// Same as part 2, but draws the flood fill in the terminal as it goes.
+pub fn solve_pt2_animated(filename: &str, settings: AnimationSettings) -> i32 {
+    let grid = parse_input(filename);
+    let mut animator = Animator::new(&grid, settings);
+
+    largest_basins_product(find_basin_sizes(&grid, Some(&mut animator)))
+}
+
+/// How much rain the cave floor can hold, once the water has drained off the edges.
+pub fn water_volume(filename: &str) -> u64 {
+    let grid = parse_input(filename);
+
+    WaterMap::fill(&grid).total_volume
+}
+
+/// Writes the hierarchy of basins to a dot file.
+pub fn write_merge_tree(filename: &str, dot_path: &str) {
+    let grid = parse_input(filename);
+
+    let tree = MergeTree::from_grid(&grid);
+    std::fs::write(dot_path, tree.to_dot()).expect("Failed to write dot file");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,4 +161,48 @@ mod tests {
         assert_eq!(solve_pt2("demo.txt"), 1134);
     }
 
+    #[test]
+    fn test_water_fill() {
+        let grid = Grid::from_string("99999\n91239\n99599\n");
+        let water = WaterMap::fill(&grid);
+        assert_eq!(water.depths[1], vec![0, 4, 3, 2, 0]);
+        // Water drains out through the 5 in the bottom wall.
+        assert_eq!(water.depths[2], vec![0, 0, 0, 0, 0]);
+        assert_eq!(water.total_volume, 9);
+    }
+
+    #[test]
+    fn test_flood_steps_merge() {
+        let grid = Grid::from_string("9999999\n9105019\n9999999\n");
+        let steps = flood_steps(&grid);
+
+        assert_eq!(steps.len(), 9);
+        assert_eq!(steps[0].pools, vec![(2, 1), (4, 1)]);
+        assert_eq!(steps[4].pools.len(), 2);
+        assert!(steps[4].merges.is_empty());
+        // The 5 in the middle goes under at level 6.
+        assert_eq!(steps[5].merges, vec![Merge { pools: vec![(2, 1), (4, 1)], into: (2, 1) }]);
+        assert_eq!(steps.last().unwrap().volume, WaterMap::fill(&grid).total_volume);
+    }
+
+    #[test]
+    fn test_merge_tree() {
+        let grid = Grid::from_string(&std::fs::read_to_string("demo.txt").unwrap());
+        let tree = MergeTree::from_grid(&grid);
+
+        // Threshold 9 gives the basins from part 2.
+        let mut sizes: Vec<usize> = tree
+            .basins_at(9)
+            .iter()
+            .map(|&b| &tree.basins[b])
+            .map(|b| b.sizes[(9 - b.appears) as usize])
+            .collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![3, 9, 9, 14]);
+
+        // Everything ends up as one basin once there are no walls.
+        assert_eq!(tree.basins_at(tree.max_threshold).len(), 1);
+        assert!(tree.basins_at(0).is_empty());
+    }
+
 }
@@ -1,17 +1,57 @@
 use advent2021_9::*;
 use std::env;
 
+fn usage(program: &str) -> ! {
+    panic!("Usage: {} <filename> [--animate] [--fps <frames per second>] [--step] [--water] [--merge-tree <dot file>]", program);
+}
+
 fn main() {
     // Get the path to the file to read
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Usage: {} <filename>", args[0]);
+
+    let mut filename = None;
+    let mut animate = false;
+    let mut fps = 20.0;
+    let mut step = false;
+    let mut water = false;
+    let mut merge_tree_path = None;
+
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--animate" => animate = true,
+            "--step" => step = true,
+            "--water" => water = true,
+            "--fps" => {
+                fps = rest
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_else(|| usage(&args[0]));
+            }
+            "--merge-tree" => merge_tree_path = Some(rest.next().unwrap_or_else(|| usage(&args[0]))),
+            _ if filename.is_none() => filename = Some(arg),
+            _ => usage(&args[0]),
+        }
     }
-    let filename = &args[1];
+    let filename = filename.unwrap_or_else(|| usage(&args[0]));
 
     let result = solve_pt1(filename);
     println!("Part 1: {result}");
 
-    let result = solve_pt2(filename);
+    let result = if animate {
+        solve_pt2_animated(filename, AnimationSettings::from_fps(fps, step))
+    } else {
+        solve_pt2(filename)
+    };
     println!("Part 2: {result}");
+
+    if water {
+        let result = water_volume(filename);
+        println!("Water volume: {result}");
+    }
+
+    if let Some(dot_path) = merge_tree_path {
+        write_merge_tree(filename, dot_path);
+        println!("Wrote merge tree to {dot_path}");
+    }
 }
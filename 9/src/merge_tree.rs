@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use crate::grid::Grid;
+use crate::DELTAS;
+
+/// A basin that exists for a range of wall thresholds.
+///
+/// At threshold `t`, every cell with a height of `t` or more is a wall, so the puzzle's basins are the ones at threshold
+/// 9. As the threshold rises, basins grow, and eventually merge together into a new basin.
+#[derive(Debug)]
+pub struct Basin {
+    /// The lowest point of the basin, which doesn't change as the basin grows.
+    pub lowest: (i32, i32),
+    /// The first threshold this basin exists at.
+    pub appears: u32,
+    /// The threshold at which this basin merges with others. It no longer exists at this threshold.
+    pub merges_at: Option<u32>,
+    /// Size of the basin at each threshold it exists at, starting from `appears`.
+    pub sizes: Vec<usize>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+impl Basin {
+    /// Whether this basin exists at a given threshold.
+    pub fn exists_at(&self, threshold: u32) -> bool {
+        threshold >= self.appears && self.merges_at.is_none_or(|m| threshold < m)
+    }
+
+    /// The height of the cells that joined this basin to the others, if it merges.
+    pub fn merge_height(&self) -> Option<u32> {
+        self.merges_at.map(|m| m - 1)
+    }
+}
+
+/// The hierarchy of basins formed as the wall threshold rises, from nothing at 0 up to the whole map being one basin.
+#[derive(Debug)]
+pub struct MergeTree {
+    pub basins: Vec<Basin>,
+    /// The threshold at which there are no walls left.
+    pub max_threshold: u32,
+}
+
+/// Union-find over the cells of the grid, to keep track of which cells are connected.
+struct DisjointSet {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+            sizes: vec![1; size],
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        // Point everything along the way straight at the root, so it's quicker next time.
+        let mut i = i;
+        while self.parents[i] != root {
+            let next = self.parents[i];
+            self.parents[i] = root;
+            i = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        let (big, small) = if self.sizes[a] >= self.sizes[b] { (a, b) } else { (b, a) };
+        self.parents[small] = big;
+        self.sizes[big] += self.sizes[small];
+    }
+}
+
+impl MergeTree {
+    pub fn from_grid(grid: &Grid) -> Self {
+        let index = |x: i32, y: i32| y as usize * grid.w + x as usize;
+
+        // Cells sorted by height, so that raising the threshold just adds the next few cells.
+        let mut cells: Vec<(u32, i32, i32)> = (0..grid.h as i32)
+            .flat_map(|y| (0..grid.w as i32).map(move |x| (x, y)))
+            .map(|(x, y)| (grid.get(x, y), y, x))
+            .collect();
+        cells.sort();
+        let max_threshold = cells.last().map_or(0, |c| c.0 + 1);
+
+        let mut sets = DisjointSet::new(grid.w * grid.h);
+        let mut included = vec![false; grid.w * grid.h];
+        let mut basins: Vec<Basin> = Vec::new();
+        // Basins that exist at the current threshold.
+        let mut alive: Vec<usize> = Vec::new();
+        let mut next_cell = 0;
+
+        for threshold in 1..=max_threshold {
+            // Let in all the cells that aren't walls any more.
+            let start = next_cell;
+            while next_cell < cells.len() && cells[next_cell].0 < threshold {
+                let (_, y, x) = cells[next_cell];
+                included[index(x, y)] = true;
+                for (dx, dy) in DELTAS.iter() {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if grid.in_bounds(nx, ny) && included[index(nx, ny)] {
+                        sets.union(index(x, y), index(nx, ny));
+                    }
+                }
+                next_cell += 1;
+            }
+
+            // Work out which of the existing basins are now part of the same set.
+            let mut groups: Vec<(usize, usize)> = alive
+                .iter()
+                .map(|&b| (sets.find(index(basins[b].lowest.0, basins[b].lowest.1)), b))
+                .collect();
+            groups.sort();
+
+            let mut next_alive = Vec::new();
+            for group in groups.chunk_by(|a, b| a.0 == b.0) {
+                if let [(_, b)] = group {
+                    next_alive.push(*b);
+                    continue;
+                }
+
+                let id = basins.len();
+                let children: Vec<usize> = group.iter().map(|&(_, b)| b).collect();
+                for &child in &children {
+                    basins[child].merges_at = Some(threshold);
+                    basins[child].parent = Some(id);
+                }
+                let lowest = children
+                    .iter()
+                    .map(|&c| basins[c].lowest)
+                    .min_by_key(|&(x, y)| (grid.get(x, y), y, x))
+                    .unwrap();
+                basins.push(Basin {
+                    lowest,
+                    appears: threshold,
+                    merges_at: None,
+                    sizes: Vec::new(),
+                    parent: None,
+                    children,
+                });
+                next_alive.push(id);
+            }
+
+            // Any new cells that didn't join an existing basin start a new one.
+            let mut claimed: HashSet<usize> = groups.iter().map(|g| g.0).collect();
+            for &(_, y, x) in &cells[start..next_cell] {
+                if claimed.insert(sets.find(index(x, y))) {
+                    next_alive.push(basins.len());
+                    basins.push(Basin {
+                        lowest: (x, y),
+                        appears: threshold,
+                        merges_at: None,
+                        sizes: Vec::new(),
+                        parent: None,
+                        children: Vec::new(),
+                    });
+                }
+            }
+
+            for &b in &next_alive {
+                let (x, y) = basins[b].lowest;
+                let root = sets.find(index(x, y));
+                let size = sets.sizes[root];
+                basins[b].sizes.push(size);
+            }
+            alive = next_alive;
+        }
+
+        Self { basins, max_threshold }
+    }
+
+    /// The basins that exist at a given threshold.
+    pub fn basins_at(&self, threshold: u32) -> Vec<usize> {
+        (0..self.basins.len())
+            .filter(|&b| self.basins[b].exists_at(threshold))
+            .collect()
+    }
+
+    /// Outputs the tree as a dot file, with each merge pointing at the basins that went into it.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        dot.push_str("digraph G {\n");
+
+        for (i, basin) in self.basins.iter().enumerate() {
+            let last = basin.merges_at.unwrap_or(self.max_threshold + 1) - 1;
+            dot.push_str(&format!(
+                "  {} [label=\"({}, {})\\nthresholds {}..={}\\nsize {}\"];\n",
+                i,
+                basin.lowest.0,
+                basin.lowest.1,
+                basin.appears,
+                last,
+                basin.sizes.last().unwrap_or(&0),
+            ));
+        }
+
+        for (i, basin) in self.basins.iter().enumerate() {
+            for &child in &basin.children {
+                let height = self.basins[child].merge_height().unwrap();
+                dot.push_str(&format!("  {} -> {} [label=\"{}\"];\n", i, child, height));
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
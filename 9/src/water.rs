@@ -0,0 +1,188 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::grid::Grid;
+use crate::DELTAS;
+
+/// How much water the heightmap holds once it's been rained on until it can't hold any more.
+///
+/// Water can drain off the edges of the map, so each cell fills up to the lowest height it would have to climb over to
+/// reach the edge.
+#[derive(Debug)]
+pub struct WaterMap {
+    /// The height of the water surface at each cell. Dry cells have a level equal to their height.
+    pub levels: Vec<Vec<u32>>,
+    /// How deep the water is at each cell.
+    pub depths: Vec<Vec<u32>>,
+    pub total_volume: u64,
+}
+
+impl WaterMap {
+    /// Fills the grid using a priority flood from the borders.
+    ///
+    /// Starting from the border cells, we always expand the lowest cell we've reached so far. A cell that gets reached
+    /// from a higher level can't drain any lower than that, so its water level is the max of the two.
+    pub fn fill(grid: &Grid) -> Self {
+        let mut levels = vec![vec![0; grid.w]; grid.h];
+        let mut visited = vec![vec![false; grid.w]; grid.h];
+        let mut to_visit = BinaryHeap::new();
+
+        for y in 0..grid.h as i32 {
+            for x in 0..grid.w as i32 {
+                let is_border = x == 0 || y == 0 || x == grid.w as i32 - 1 || y == grid.h as i32 - 1;
+                if is_border {
+                    to_visit.push(Reverse((grid.get(x, y), x, y)));
+                }
+            }
+        }
+
+        while let Some(Reverse((level, x, y))) = to_visit.pop() {
+            if visited[y as usize][x as usize] {
+                continue;
+            }
+            visited[y as usize][x as usize] = true;
+            levels[y as usize][x as usize] = level;
+
+            for (dx, dy) in DELTAS.iter() {
+                let (nx, ny) = (x + dx, y + dy);
+                if !grid.in_bounds(nx, ny) || visited[ny as usize][nx as usize] {
+                    continue;
+                }
+                to_visit.push(Reverse((level.max(grid.get(nx, ny)), nx, ny)));
+            }
+        }
+
+        Self::from_levels(grid, levels)
+    }
+
+    fn from_levels(grid: &Grid, levels: Vec<Vec<u32>>) -> Self {
+        let depths: Vec<Vec<u32>> = levels
+            .iter()
+            .enumerate()
+            .map(|(y, r)| {
+                r.iter()
+                    .enumerate()
+                    .map(|(x, level)| level - grid.get(x as i32, y as i32))
+                    .collect()
+            })
+            .collect();
+        let total_volume = depths.iter().flatten().map(|&d| d as u64).sum();
+
+        Self { levels, depths, total_volume }
+    }
+}
+
+/// A few pools of water joining up into one.
+#[derive(Debug, PartialEq)]
+pub struct Merge {
+    /// The lowest point of each pool that merged, which is used to identify them.
+    pub pools: Vec<(i32, i32)>,
+    /// The lowest point of the new pool.
+    pub into: (i32, i32),
+}
+
+/// The state of the water after raising it to a particular level.
+#[derive(Debug)]
+pub struct FloodStep {
+    pub level: u32,
+    pub volume: u64,
+    /// The lowest point of each separate pool of water.
+    pub pools: Vec<(i32, i32)>,
+    /// Pools that were separate in the previous step, and have now joined together.
+    pub merges: Vec<Merge>,
+}
+
+/// Raises the water one unit at a time, until the map is as full as it can be.
+///
+/// At each step the water is at most `level` high, and never higher than it would be in the filled [`WaterMap`], so the
+/// last step has the same volume as the filled map.
+pub fn flood_steps(grid: &Grid) -> Vec<FloodStep> {
+    let filled = WaterMap::fill(grid);
+    let max_level = filled.levels.iter().flatten().copied().max().unwrap_or(0);
+
+    let mut steps: Vec<FloodStep> = Vec::new();
+    // Which pool each cell was in during the previous step, as an index into the previous step's pools.
+    let mut prev_pool_ids: PoolIds = vec![vec![None; grid.w]; grid.h];
+
+    for level in 1..=max_level {
+        let levels: Vec<Vec<u32>> = filled
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(y, r)| {
+                r.iter()
+                    .enumerate()
+                    .map(|(x, &l)| l.min(level).max(grid.get(x as i32, y as i32)))
+                    .collect()
+            })
+            .collect();
+        let water = WaterMap::from_levels(grid, levels);
+
+        let (pool_ids, pools) = find_pools(grid, &water.depths);
+
+        let prev_pools = steps.last().map(|s| s.pools.as_slice()).unwrap_or(&[]);
+        // Pair up each cell's pool now with the pool it was in before, so we can see which old pools ended up together.
+        let mut links: Vec<(usize, usize)> = pool_ids
+            .iter()
+            .flatten()
+            .zip(prev_pool_ids.iter().flatten())
+            .filter_map(|(id, prev)| Some(((*id)?, (*prev)?)))
+            .collect();
+        links.sort();
+        links.dedup();
+
+        let mut merges = Vec::new();
+        for group in links.chunk_by(|a, b| a.0 == b.0) {
+            if group.len() > 1 {
+                let merged = group.iter().map(|&(_, prev)| prev_pools[prev]).collect();
+                merges.push(Merge { pools: merged, into: pools[group[0].0] });
+            }
+        }
+
+        steps.push(FloodStep { level, volume: water.total_volume, pools, merges });
+        prev_pool_ids = pool_ids;
+    }
+
+    steps
+}
+
+/// Which pool each cell is in, if it's underwater.
+type PoolIds = Vec<Vec<Option<usize>>>;
+
+/// Finds the connected regions of cells that are underwater.
+///
+/// Returns the pool that each cell is in, and the lowest point of each pool.
+fn find_pools(grid: &Grid, depths: &[Vec<u32>]) -> (PoolIds, Vec<(i32, i32)>) {
+    let mut pool_ids = vec![vec![None; grid.w]; grid.h];
+    let mut pools = Vec::new();
+
+    for y in 0..grid.h as i32 {
+        for x in 0..grid.w as i32 {
+            if depths[y as usize][x as usize] == 0 || pool_ids[y as usize][x as usize].is_some() {
+                continue;
+            }
+
+            let id = pools.len();
+            let mut lowest = (x, y);
+            let mut to_visit = vec![(x, y)];
+            while let Some((x, y)) = to_visit.pop() {
+                if !grid.in_bounds(x, y)
+                    || depths[y as usize][x as usize] == 0
+                    || pool_ids[y as usize][x as usize].is_some()
+                {
+                    continue;
+                }
+                pool_ids[y as usize][x as usize] = Some(id);
+
+                let is_lower = (grid.get(x, y), y, x) < (grid.get(lowest.0, lowest.1), lowest.1, lowest.0);
+                if is_lower {
+                    lowest = (x, y);
+                }
+
+                to_visit.extend(DELTAS.iter().map(|(dx, dy)| (x + dx, y + dy)));
+            }
+            pools.push(lowest);
+        }
+    }
+
+    (pool_ids, pools)
+}